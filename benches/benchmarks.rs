@@ -5,28 +5,19 @@ use pgn_parser::game;
 use pgn_parser::pgn_tree_builder;
 use pgn_parser::pgn_tokenizer;
 
-use std::io::BufRead;
-
 fn make_builder(file: String) -> pgn_tree_builder::PGNTreeBuilder {
-	let mut entire_file_str = String::new();
-
-	let file = std::fs::File::open(file).expect("Failed to open file");
-	let reader = std::io::BufReader::new(file);
-	for line in reader.lines() {
-		entire_file_str.push_str( line.unwrap().trim() );
-	}
-
-	let (all_tokens, all_token_types) = pgn_tokenizer::tokenize(entire_file_str);
+	let (all_tokens, all_token_types, all_token_spans) =
+		pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
 
 	let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
-	builder.set_token_list(all_tokens, all_token_types);
+	builder.set_token_list(all_tokens, all_token_types, all_token_spans);
 
 	builder
 }
 
 fn make_game(file: String) -> game::GameTree {
 	let mut builder = make_builder(file);
-	if let Some(g) = builder.build_game_tree(0) {
+	if let Ok(Some(g)) = builder.build_game_tree(0) {
 		return g;
 	}
 
@@ -0,0 +1,785 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::pgn_tokenizer;
+use crate::pgn_tokenizer::Side;
+
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub enum PieceType { Pawn, Knight, Bishop, Rook, Queen, King }
+
+pub fn classify_piece(c: char) -> Option<PieceType> {
+	match c {
+		'P' => Some(PieceType::Pawn),
+		'N' => Some(PieceType::Knight),
+		'B' => Some(PieceType::Bishop),
+		'R' => Some(PieceType::Rook),
+		'Q' => Some(PieceType::Queen),
+		'K' => Some(PieceType::King),
+		_ => None
+	}
+}
+
+pub fn piece_to_char(p: &PieceType) -> char {
+	match p {
+		PieceType::Pawn => 'P',
+		PieceType::Knight => 'N',
+		PieceType::Bishop => 'B',
+		PieceType::Rook => 'R',
+		PieceType::Queen => 'Q',
+		PieceType::King => 'K'
+	}
+}
+
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub struct Piece {
+	m_type: PieceType,
+	m_side: Side
+}
+
+impl Piece {
+	pub fn new(t: PieceType, s: Side) -> Piece {
+		Piece { m_type: t, m_side: s }
+	}
+
+	pub fn get_type(&self) -> PieceType { self.m_type }
+	pub fn get_side(&self) -> Side { self.m_side }
+}
+
+// A square of the board. Files and ranks start at 0, so that 'a1' is the
+// square (0,0) and 'h8' is the square (7,7).
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub struct Square {
+	m_index: u8
+}
+
+impl Square {
+	pub fn new(file: u8, rank: u8) -> Square {
+		assert!(file < 8 && rank < 8);
+		Square { m_index: rank*8 + file }
+	}
+
+	pub fn get_file(&self) -> u8 { self.m_index%8 }
+	pub fn get_rank(&self) -> u8 { self.m_index/8 }
+	pub fn get_index(&self) -> usize { self.m_index as usize }
+
+	// The square at the given distance from this one, if it is on the board.
+	pub fn offset(&self, df: i8, dr: i8) -> Option<Square> {
+		let file = self.get_file() as i8 + df;
+		let rank = self.get_rank() as i8 + dr;
+		if (0..8).contains(&file) && (0..8).contains(&rank) {
+			Some(Square::new(file as u8, rank as u8))
+		}
+		else {
+			None
+		}
+	}
+}
+
+impl std::fmt::Display for Square {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}{}", (b'a' + self.get_file()) as char, self.get_rank() + 1)
+	}
+}
+
+pub fn parse_square(s: &str) -> Option<Square> {
+	let mut chars = s.chars();
+	let file = chars.next()?;
+	let rank = chars.next()?;
+	if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+		return None;
+	}
+	Some(Square::new(file as u8 - b'a', rank as u8 - b'1'))
+}
+
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub struct Move {
+	m_from: Square,
+	m_to: Square,
+	m_promotion: Option<PieceType>
+}
+
+impl Move {
+	pub fn new(from: Square, to: Square, promotion: Option<PieceType>) -> Move {
+		Move { m_from: from, m_to: to, m_promotion: promotion }
+	}
+
+	pub fn get_from(&self) -> Square { self.m_from }
+	pub fn get_to(&self) -> Square { self.m_to }
+	pub fn get_promotion(&self) -> Option<PieceType> { self.m_promotion }
+}
+
+// Long algebraic notation of a move, e.g. 'e2e4' or 'e7e8q'.
+impl std::fmt::Display for Move {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}{}", self.m_from, self.m_to)?;
+		if let Some(p) = &self.m_promotion {
+			write!(f, "{}", piece_to_char(p).to_ascii_lowercase())?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub enum MoveErrorKind {
+	// The text is not a move in Standard Algebraic Notation.
+	InvalidSan,
+	// The move cannot be played in the position.
+	IllegalMove,
+	// More than one move in the position matches the text.
+	AmbiguousMove
+}
+
+// An error found while replaying the moves of a game. It identifies the move
+// by its number, side, text and position in the source.
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub struct MoveError {
+	m_kind: MoveErrorKind,
+	m_move_number: u16,
+	m_side: Side,
+	m_move_text: String,
+	m_span: Option<pgn_tokenizer::Span>
+}
+
+impl MoveError {
+	pub fn new(
+		kind: MoveErrorKind,
+		move_number: u16,
+		side: Side,
+		move_text: String,
+		span: Option<pgn_tokenizer::Span>
+	)
+	-> MoveError
+	{
+		MoveError {
+			m_kind: kind,
+			m_move_number: move_number,
+			m_side: side,
+			m_move_text: move_text,
+			m_span: span
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_kind(&self) -> &MoveErrorKind { &self.m_kind }
+	pub fn get_move_number(&self) -> u16 { self.m_move_number }
+	pub fn get_side(&self) -> &Side { &self.m_side }
+	pub fn get_move_text(&self) -> &String { &self.m_move_text }
+	pub fn get_span(&self) -> &Option<pgn_tokenizer::Span> { &self.m_span }
+}
+
+impl std::fmt::Display for MoveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let what = match &self.m_kind {
+			MoveErrorKind::InvalidSan => "Invalid move",
+			MoveErrorKind::IllegalMove => "Illegal move",
+			MoveErrorKind::AmbiguousMove => "Ambiguous move",
+		};
+		let dots = if self.m_side == Side::White { "." } else { "..." };
+		write!(f, "{what} {}{dots} {}", self.m_move_number, self.m_move_text)?;
+		if let Some(span) = &self.m_span {
+			write!(f, " (line {}, column {})", span.get_line(), span.get_column())?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for MoveError {}
+
+const KNIGHT_STEPS: [(i8, i8); 8] =
+	[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i8, i8); 8] =
+	[(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+const WHITE_KING_SIDE: usize = 0;
+const WHITE_QUEEN_SIDE: usize = 1;
+const BLACK_KING_SIDE: usize = 2;
+const BLACK_QUEEN_SIDE: usize = 3;
+
+fn castling_index(s: Side, king_side: bool) -> usize {
+	match (s, king_side) {
+		(Side::White, true) => WHITE_KING_SIDE,
+		(Side::White, false) => WHITE_QUEEN_SIDE,
+		(Side::Black, true) => BLACK_KING_SIDE,
+		(Side::Black, false) => BLACK_QUEEN_SIDE
+	}
+}
+
+fn home_rank(s: Side) -> u8 {
+	if s == Side::White { 0 } else { 7 }
+}
+
+fn pawn_direction(s: Side) -> i8 {
+	if s == Side::White { 1 } else { -1 }
+}
+
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub struct Position {
+	m_board: [Option<Piece>; 64],
+	m_side_to_move: Side,
+	m_castling: [bool; 4],
+	m_en_passant: Option<Square>,
+	m_halfmove_clock: u16,
+	m_fullmove_number: u16
+}
+
+impl Default for Position {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Position {
+	// The standard starting position.
+	pub fn new() -> Position {
+		let mut p = Position::new_empty();
+
+		let back_rank = [
+			PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen,
+			PieceType::King, PieceType::Bishop, PieceType::Knight, PieceType::Rook
+		];
+		for (file, t) in back_rank.iter().enumerate() {
+			let file = file as u8;
+			p.set_piece(Square::new(file, 0), Some(Piece::new(*t, Side::White)));
+			p.set_piece(Square::new(file, 1), Some(Piece::new(PieceType::Pawn, Side::White)));
+			p.set_piece(Square::new(file, 6), Some(Piece::new(PieceType::Pawn, Side::Black)));
+			p.set_piece(Square::new(file, 7), Some(Piece::new(*t, Side::Black)));
+		}
+		p.m_castling = [true; 4];
+		p
+	}
+
+	// A position without pieces, with White to move.
+	pub fn new_empty() -> Position {
+		Position {
+			m_board: [None; 64],
+			m_side_to_move: Side::White,
+			m_castling: [false; 4],
+			m_en_passant: None,
+			m_halfmove_clock: 0,
+			m_fullmove_number: 1
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_piece(&self, sq: Square) -> Option<Piece> { self.m_board[sq.get_index()] }
+	pub fn get_side_to_move(&self) -> Side { self.m_side_to_move }
+	pub fn get_en_passant(&self) -> Option<Square> { self.m_en_passant }
+	pub fn get_halfmove_clock(&self) -> u16 { self.m_halfmove_clock }
+	pub fn get_fullmove_number(&self) -> u16 { self.m_fullmove_number }
+	pub fn can_castle(&self, s: Side, king_side: bool) -> bool {
+		self.m_castling[castling_index(s, king_side)]
+	}
+
+	/* MODIFIERS */
+
+	pub fn set_piece(&mut self, sq: Square, p: Option<Piece>) {
+		self.m_board[sq.get_index()] = p;
+	}
+	pub fn set_side_to_move(&mut self, s: Side) {
+		self.m_side_to_move = s;
+	}
+	pub fn set_castling(&mut self, s: Side, king_side: bool, v: bool) {
+		self.m_castling[castling_index(s, king_side)] = v;
+	}
+	pub fn set_en_passant(&mut self, sq: Option<Square>) {
+		self.m_en_passant = sq;
+	}
+	pub fn set_halfmove_clock(&mut self, v: u16) {
+		self.m_halfmove_clock = v;
+	}
+	pub fn set_fullmove_number(&mut self, v: u16) {
+		self.m_fullmove_number = v;
+	}
+
+	/* ATTACKS */
+
+	fn find_king(&self, s: Side) -> Option<Square> {
+		(0..64)
+			.map(|i| Square { m_index: i })
+			.find(|sq| self.get_piece(*sq) == Some(Piece::new(PieceType::King, s)))
+	}
+
+	fn is_piece_at(&self, sq: Option<Square>, t: PieceType, s: Side) -> bool {
+		sq.is_some_and(|sq| self.get_piece(sq) == Some(Piece::new(t, s)))
+	}
+
+	// Whether the square is attacked by any piece of side 'by'.
+	pub fn is_attacked(&self, sq: Square, by: Side) -> bool {
+		let dir = pawn_direction(by);
+		if
+			self.is_piece_at(sq.offset(-1, -dir), PieceType::Pawn, by) ||
+			self.is_piece_at(sq.offset(1, -dir), PieceType::Pawn, by)
+		{
+			return true;
+		}
+		for (df, dr) in KNIGHT_STEPS.iter() {
+			if self.is_piece_at(sq.offset(*df, *dr), PieceType::Knight, by) {
+				return true;
+			}
+		}
+		for (df, dr) in KING_STEPS.iter() {
+			if self.is_piece_at(sq.offset(*df, *dr), PieceType::King, by) {
+				return true;
+			}
+		}
+
+		let sliders = [
+			(&BISHOP_DIRECTIONS, PieceType::Bishop),
+			(&ROOK_DIRECTIONS, PieceType::Rook)
+		];
+		for (directions, t) in sliders.iter() {
+			for (df, dr) in directions.iter() {
+				let mut next = sq.offset(*df, *dr);
+				while let Some(n) = next {
+					if let Some(p) = self.get_piece(n) {
+						if
+							p.get_side() == by &&
+							(p.get_type() == *t || p.get_type() == PieceType::Queen)
+						{
+							return true;
+						}
+						break;
+					}
+					next = n.offset(*df, *dr);
+				}
+			}
+		}
+		false
+	}
+
+	// Whether the side to move is in check.
+	pub fn is_in_check(&self) -> bool {
+		let s = self.m_side_to_move;
+		self.find_king(s).is_some_and(|k| self.is_attacked(k, pgn_tokenizer::other_side(&s)))
+	}
+
+	/* MOVE GENERATION */
+
+	fn add_pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+		let s = self.m_side_to_move;
+		let dir = pawn_direction(s);
+		let last_rank = home_rank(pgn_tokenizer::other_side(&s));
+		let start_rank = if s == Side::White { 1 } else { 6 };
+
+		let mut add = |to: Square| {
+			if to.get_rank() == last_rank {
+				for t in [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+					moves.push(Move::new(from, to, Some(t)));
+				}
+			}
+			else {
+				moves.push(Move::new(from, to, None));
+			}
+		};
+
+		if let Some(one) = from.offset(0, dir) {
+			if self.get_piece(one).is_none() {
+				add(one);
+				if from.get_rank() == start_rank {
+					let two = one.offset(0, dir).unwrap();
+					if self.get_piece(two).is_none() {
+						add(two);
+					}
+				}
+			}
+		}
+		for df in [-1, 1] {
+			if let Some(to) = from.offset(df, dir) {
+				let captures = self.get_piece(to).is_some_and(|p| p.get_side() != s);
+				if captures || self.m_en_passant == Some(to) {
+					add(to);
+				}
+			}
+		}
+	}
+
+	fn add_step_moves(&self, from: Square, steps: &[(i8, i8)], moves: &mut Vec<Move>) {
+		for (df, dr) in steps.iter() {
+			if let Some(to) = from.offset(*df, *dr) {
+				if self.get_piece(to).is_none_or(|p| p.get_side() != self.m_side_to_move) {
+					moves.push(Move::new(from, to, None));
+				}
+			}
+		}
+	}
+
+	fn add_slider_moves(&self, from: Square, directions: &[(i8, i8)], moves: &mut Vec<Move>) {
+		for (df, dr) in directions.iter() {
+			let mut next = from.offset(*df, *dr);
+			while let Some(to) = next {
+				match self.get_piece(to) {
+					None => moves.push(Move::new(from, to, None)),
+					Some(p) => {
+						if p.get_side() != self.m_side_to_move {
+							moves.push(Move::new(from, to, None));
+						}
+						break;
+					}
+				}
+				next = to.offset(*df, *dr);
+			}
+		}
+	}
+
+	fn add_castling_moves(&self, moves: &mut Vec<Move>) {
+		let s = self.m_side_to_move;
+		let opponent = pgn_tokenizer::other_side(&s);
+		let rank = home_rank(s);
+		let king = Square::new(4, rank);
+		if !self.is_piece_at(Some(king), PieceType::King, s) || self.is_attacked(king, opponent) {
+			return;
+		}
+
+		// (king side, rook file, squares that must be empty, squares the king crosses)
+		let options: [(bool, u8, &[u8], &[u8]); 2] = [
+			(true, 7, &[5, 6], &[5, 6]),
+			(false, 0, &[1, 2, 3], &[3, 2])
+		];
+		for (king_side, rook_file, empty, crossed) in options.iter() {
+			if
+				self.can_castle(s, *king_side) &&
+				self.is_piece_at(Some(Square::new(*rook_file, rank)), PieceType::Rook, s) &&
+				empty.iter().all(|f| self.get_piece(Square::new(*f, rank)).is_none()) &&
+				crossed.iter().all(|f| !self.is_attacked(Square::new(*f, rank), opponent))
+			{
+				let to_file = if *king_side { 6 } else { 2 };
+				moves.push(Move::new(king, Square::new(to_file, rank), None));
+			}
+		}
+	}
+
+	fn pseudo_legal_moves(&self) -> Vec<Move> {
+		let mut moves = Vec::new();
+		for i in 0..64 {
+			let from = Square { m_index: i };
+			let p = match self.get_piece(from) {
+				Some(p) if p.get_side() == self.m_side_to_move => p,
+				_ => continue
+			};
+			match p.get_type() {
+				PieceType::Pawn => self.add_pawn_moves(from, &mut moves),
+				PieceType::Knight => self.add_step_moves(from, &KNIGHT_STEPS, &mut moves),
+				PieceType::King => self.add_step_moves(from, &KING_STEPS, &mut moves),
+				PieceType::Bishop => self.add_slider_moves(from, &BISHOP_DIRECTIONS, &mut moves),
+				PieceType::Rook => self.add_slider_moves(from, &ROOK_DIRECTIONS, &mut moves),
+				PieceType::Queen => {
+					self.add_slider_moves(from, &BISHOP_DIRECTIONS, &mut moves);
+					self.add_slider_moves(from, &ROOK_DIRECTIONS, &mut moves);
+				}
+			}
+		}
+		self.add_castling_moves(&mut moves);
+		moves
+	}
+
+	pub fn legal_moves(&self) -> Vec<Move> {
+		let s = self.m_side_to_move;
+		self.pseudo_legal_moves()
+			.into_iter()
+			.filter(
+				|m| {
+					let mut next = self.clone();
+					next.play(m);
+					next.find_king(s).is_some_and(
+						|k| !next.is_attacked(k, pgn_tokenizer::other_side(&s))
+					)
+				}
+			)
+			.collect()
+	}
+
+	// Plays the move on the board. The move is assumed to be legal.
+	pub fn play(&mut self, m: &Move) {
+		let s = self.m_side_to_move;
+		let from = m.get_from();
+		let to = m.get_to();
+		let piece = self.get_piece(from).expect("There is no piece to move");
+
+		let mut is_capture = self.get_piece(to).is_some();
+		if
+			piece.get_type() == PieceType::Pawn &&
+			self.m_en_passant == Some(to) &&
+			from.get_file() != to.get_file() &&
+			!is_capture
+		{
+			self.set_piece(Square::new(to.get_file(), from.get_rank()), None);
+			is_capture = true;
+		}
+
+		if piece.get_type() == PieceType::King && from.get_file().abs_diff(to.get_file()) == 2 {
+			let (rook_from, rook_to) = if to.get_file() == 6 { (7, 5) } else { (0, 3) };
+			let rook = self.get_piece(Square::new(rook_from, from.get_rank()));
+			self.set_piece(Square::new(rook_from, from.get_rank()), None);
+			self.set_piece(Square::new(rook_to, from.get_rank()), rook);
+		}
+
+		self.set_piece(from, None);
+		self.set_piece(
+			to,
+			Some(m.get_promotion().map_or(piece, |t| Piece::new(t, s)))
+		);
+
+		if piece.get_type() == PieceType::King {
+			self.set_castling(s, true, false);
+			self.set_castling(s, false, false);
+		}
+		for sq in [from, to] {
+			for side in [Side::White, Side::Black] {
+				if sq == Square::new(7, home_rank(side)) {
+					self.set_castling(side, true, false);
+				}
+				if sq == Square::new(0, home_rank(side)) {
+					self.set_castling(side, false, false);
+				}
+			}
+		}
+
+		self.m_en_passant =
+			if piece.get_type() == PieceType::Pawn && from.get_rank().abs_diff(to.get_rank()) == 2 {
+				from.offset(0, pawn_direction(s))
+			}
+			else {
+				None
+			};
+
+		if piece.get_type() == PieceType::Pawn || is_capture {
+			self.m_halfmove_clock = 0;
+		}
+		else {
+			self.m_halfmove_clock = self.m_halfmove_clock.saturating_add(1);
+		}
+		if s == Side::Black {
+			self.m_fullmove_number = self.m_fullmove_number.saturating_add(1);
+		}
+		self.m_side_to_move = pgn_tokenizer::other_side(&s);
+	}
+
+	// Passes the turn to the other side without moving any piece.
+	pub fn play_null_move(&mut self) {
+		let s = self.m_side_to_move;
+		self.m_en_passant = None;
+		self.m_halfmove_clock = self.m_halfmove_clock.saturating_add(1);
+		if s == Side::Black {
+			self.m_fullmove_number = self.m_fullmove_number.saturating_add(1);
+		}
+		self.m_side_to_move = pgn_tokenizer::other_side(&s);
+	}
+
+	/* FORSYTH-EDWARDS NOTATION */
+
+	pub fn to_fen(&self) -> String {
+		let mut fen = String::new();
+		for rank in (0..8).rev() {
+			let mut empty = 0;
+			for file in 0..8 {
+				match self.get_piece(Square::new(file, rank)) {
+					None => empty += 1,
+					Some(p) => {
+						if empty > 0 {
+							fen.push_str(&empty.to_string());
+							empty = 0;
+						}
+						let c = piece_to_char(&p.get_type());
+						fen.push(if p.get_side() == Side::White { c } else { c.to_ascii_lowercase() });
+					}
+				}
+			}
+			if empty > 0 {
+				fen.push_str(&empty.to_string());
+			}
+			if rank > 0 {
+				fen.push('/');
+			}
+		}
+
+		fen.push_str(if self.m_side_to_move == Side::White { " w " } else { " b " });
+
+		let castling: String = ['K', 'Q', 'k', 'q']
+			.iter()
+			.zip(self.m_castling.iter())
+			.filter(|(_, v)| **v)
+			.map(|(c, _)| *c)
+			.collect();
+		fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+		fen.push(' ');
+		match &self.m_en_passant {
+			Some(sq) => fen.push_str(&sq.to_string()),
+			None => fen.push('-')
+		}
+
+		fen.push_str(&format!(" {} {}", self.m_halfmove_clock, self.m_fullmove_number));
+		fen
+	}
+
+	/* STANDARD ALGEBRAIC NOTATION */
+
+	// Finds the legal move described by the text in Standard Algebraic
+	// Notation. Check, mate and annotation suffixes are ignored.
+	pub fn parse_san(&self, san: &str) -> Result<Move, MoveErrorKind> {
+		static SAN_REGEX: OnceLock<Regex> = OnceLock::new();
+		let re = SAN_REGEX.get_or_init(
+			|| Regex::new(
+				r"^(?<piece>[NBRQK])?(?<file>[a-h])?(?<rank>[1-8])?x?(?<to>[a-h][1-8])(=?(?<promotion>[NBRQ]))?$"
+			).unwrap()
+		);
+
+		let san = san.trim_end_matches(['+', '#', '!', '?']);
+		let s = self.m_side_to_move;
+		let legal = self.legal_moves();
+
+		let castling = match san {
+			"O-O" | "0-0" => Some(6),
+			"O-O-O" | "0-0-0" => Some(2),
+			_ => None
+		};
+		let candidates: Vec<Move> =
+		if let Some(to_file) = castling {
+			let king = Square::new(4, home_rank(s));
+			let to = Square::new(to_file, home_rank(s));
+			if !self.is_piece_at(Some(king), PieceType::King, s) {
+				return Err(MoveErrorKind::IllegalMove);
+			}
+			legal.into_iter().filter(|m| m.get_from() == king && m.get_to() == to).collect()
+		}
+		else {
+			let capture = re.captures(san).ok_or(MoveErrorKind::InvalidSan)?;
+
+			let piece = capture.name("piece")
+				.map_or(PieceType::Pawn, |p| classify_piece(p.as_str().chars().next().unwrap()).unwrap());
+			let file = capture.name("file").map(|f| f.as_str().as_bytes()[0] - b'a');
+			let rank = capture.name("rank").map(|r| r.as_str().as_bytes()[0] - b'1');
+			let to = parse_square(&capture["to"]).unwrap();
+			let promotion = capture.name("promotion")
+				.map(|p| classify_piece(p.as_str().chars().next().unwrap()).unwrap());
+
+			legal
+				.into_iter()
+				.filter(
+					|m|
+					m.get_to() == to &&
+					m.get_promotion() == promotion &&
+					self.get_piece(m.get_from()).is_some_and(|p| p.get_type() == piece) &&
+					file.is_none_or(|f| m.get_from().get_file() == f) &&
+					rank.is_none_or(|r| m.get_from().get_rank() == r)
+				)
+				.collect()
+		};
+
+		match candidates.len() {
+			0 => Err(MoveErrorKind::IllegalMove),
+			1 => Ok(candidates[0]),
+			_ => Err(MoveErrorKind::AmbiguousMove)
+		}
+	}
+}
+
+// Parses a position in Forsyth-Edwards Notation. The half-move clock and
+// the full-move number may be omitted.
+pub fn parse_fen(fen: &str) -> Option<Position> {
+	let mut fields = fen.split_whitespace();
+	let mut p = Position::new_empty();
+
+	let placement = fields.next()?;
+	let ranks: Vec<&str> = placement.split('/').collect();
+	if ranks.len() != 8 {
+		return None;
+	}
+	for (i, rank_str) in ranks.iter().enumerate() {
+		let rank = 7 - i as u8;
+		let mut file: u8 = 0;
+		for c in rank_str.chars() {
+			if let Some(d) = c.to_digit(10) {
+				if d == 0 || file + d as u8 > 8 {
+					return None;
+				}
+				file += d as u8;
+			}
+			else {
+				let t = classify_piece(c.to_ascii_uppercase())?;
+				let s = if c.is_ascii_uppercase() { Side::White } else { Side::Black };
+				if file >= 8 {
+					return None;
+				}
+				p.set_piece(Square::new(file, rank), Some(Piece::new(t, s)));
+				file += 1;
+			}
+		}
+		if file != 8 {
+			return None;
+		}
+	}
+
+	p.m_side_to_move = match fields.next()? {
+		"w" => Side::White,
+		"b" => Side::Black,
+		_ => return None
+	};
+
+	let castling = fields.next()?;
+	if castling != "-" {
+		for c in castling.chars() {
+			match c {
+				'K' => p.set_castling(Side::White, true, true),
+				'Q' => p.set_castling(Side::White, false, true),
+				'k' => p.set_castling(Side::Black, true, true),
+				'q' => p.set_castling(Side::Black, false, true),
+				_ => return None
+			}
+		}
+	}
+
+	let en_passant = fields.next()?;
+	if en_passant != "-" {
+		p.m_en_passant = Some(parse_square(en_passant)?);
+	}
+
+	if let Some(halfmove) = fields.next() {
+		p.m_halfmove_clock = halfmove.parse::<u16>().ok()?;
+	}
+	if let Some(fullmove) = fields.next() {
+		p.m_fullmove_number = fullmove.parse::<u16>().ok()?;
+	}
+	if fields.next().is_some() {
+		return None;
+	}
+
+	Some(p)
+}
@@ -30,44 +30,339 @@
  *
  ********************************************************************/
 
- #[derive(Debug,Eq,PartialEq)]
+use std::time::Duration;
+
+use crate::board;
+
+#[derive(Debug,Eq,PartialEq)]
 pub enum TagType {
 	Clock,
+	ElapsedMoveTime,
 	Eval,
+	Arrows,
+	Highlights,
 	Other(String)
 }
 
 pub fn classify_tag(s: String) -> TagType {
-	if s == "%clk".to_string() { return TagType::Clock; }
-	if s == "%eval".to_string() { return TagType::Eval; }
-	return TagType::Other(s);
+	if s == "%clk" { return TagType::Clock; }
+	if s == "%emt" { return TagType::ElapsedMoveTime; }
+	if s == "%eval" { return TagType::Eval; }
+	if s == "%cal" { return TagType::Arrows; }
+	if s == "%csl" { return TagType::Highlights; }
+	TagType::Other(s)
 }
 
 pub fn tag_to_string(t: &TagType) -> String {
 	match &t {
 		TagType::Clock => "%clk".to_string(),
+		TagType::ElapsedMoveTime => "%emt".to_string(),
 		TagType::Eval => "%eval".to_string(),
+		TagType::Arrows => "%cal".to_string(),
+		TagType::Highlights => "%csl".to_string(),
 		TagType::Other(s) => s.clone()
 	}
 }
 
-#[derive(Debug,Eq,PartialEq)]
+// Parses a time of the form 'H:MM:SS', 'M:SS' or 'S', where the seconds
+// may have a fractional part, e.g. '0:02:59.9'.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+	let fields: Vec<&str> = s.split(':').collect();
+	if fields.len() > 3 {
+		return None;
+	}
+
+	let (seconds, nanos) = match fields[fields.len() - 1].split_once('.') {
+		Some((seconds, fraction)) => {
+			if fraction.is_empty() || fraction.len() > 9 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+				return None;
+			}
+			let nanos = fraction.parse::<u32>().ok()? * 10u32.pow(9 - fraction.len() as u32);
+			(seconds, nanos)
+		},
+		None => (fields[fields.len() - 1], 0)
+	};
+	let parse_field = |f: &str| -> Option<u64> {
+		if f.is_empty() || !f.chars().all(|c| c.is_ascii_digit()) {
+			return None;
+		}
+		f.parse::<u64>().ok()
+	};
+
+	let mut total = parse_field(seconds)?;
+	if fields.len() >= 2 && total >= 60 {
+		return None;
+	}
+	if fields.len() >= 2 {
+		let minutes = parse_field(fields[fields.len() - 2])?;
+		if fields.len() == 3 && minutes >= 60 {
+			return None;
+		}
+		total = total.checked_add(minutes.checked_mul(60)?)?;
+	}
+	if fields.len() == 3 {
+		total = total.checked_add(parse_field(fields[0])?.checked_mul(3600)?)?;
+	}
+	Some(Duration::new(total, nanos))
+}
+
+// Formats a time as 'H:MM:SS', followed by the fractional part of the
+// seconds, if any.
+pub fn duration_to_string(d: &Duration) -> String {
+	let total = d.as_secs();
+	let mut s = format!("{}:{:02}:{:02}", total/3600, (total/60)%60, total%60);
+	if d.subsec_nanos() > 0 {
+		let fraction = format!("{:09}", d.subsec_nanos());
+		s.push('.');
+		s.push_str(fraction.trim_end_matches('0'));
+	}
+	s
+}
+
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub enum Score {
+	// Positive values favour White.
+	Centipawns(i32),
+	// Number of moves to mate. Negative values mean that Black mates.
+	MateIn(i32)
+}
+
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub struct Evaluation {
+	m_score: Score,
+	m_depth: Option<u16>
+}
+
+impl Evaluation {
+	pub fn new(score: Score, depth: Option<u16>) -> Evaluation {
+		Evaluation {
+			m_score: score,
+			m_depth: depth
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_score(&self) -> Score { self.m_score }
+	// The depth of the search that gave the evaluation, if known.
+	pub fn get_depth(&self) -> Option<u16> { self.m_depth }
+	pub fn get_centipawns(&self) -> Option<i32> {
+		match self.m_score {
+			Score::Centipawns(cp) => Some(cp),
+			Score::MateIn(_) => None
+		}
+	}
+	pub fn get_mate_in(&self) -> Option<i32> {
+		match self.m_score {
+			Score::Centipawns(_) => None,
+			Score::MateIn(n) => Some(n)
+		}
+	}
+}
+
+fn is_signed_number(s: &str, allow_point: bool) -> bool {
+	let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+	let mut num_points = 0;
+	let mut num_digits = 0;
+	for c in digits.chars() {
+		match c {
+			'0'..='9' => num_digits += 1,
+			'.' if allow_point => num_points += 1,
+			_ => return false
+		}
+	}
+	num_digits > 0 && num_points <= 1
+}
+
+// Parses an evaluation in pawns such as '0.17' or '-1.5', or a mate score
+// such as '#3' or '#-2', optionally followed by the depth, as in '0.17,20'.
+pub fn parse_evaluation(s: &str) -> Option<Evaluation> {
+	let (score, depth) = match s.split_once(',') {
+		Some((score, depth)) => {
+			if depth.is_empty() || !depth.chars().all(|c| c.is_ascii_digit()) {
+				return None;
+			}
+			(score, Some(depth.parse::<u16>().ok()?))
+		},
+		None => (s, None)
+	};
+
+	let score = match score.strip_prefix('#') {
+		Some(mate) => {
+			if !is_signed_number(mate, false) {
+				return None;
+			}
+			Score::MateIn(mate.parse::<i32>().ok()?)
+		},
+		None => {
+			if !is_signed_number(score, true) {
+				return None;
+			}
+			let pawns = score.parse::<f64>().ok()?;
+			Score::Centipawns((pawns*100.0).round() as i32)
+		}
+	};
+	Some(Evaluation::new(score, depth))
+}
+
+// Formats an evaluation in pawns with at most two decimals, e.g. '0.17',
+// '-1.5' or '0.0', or as a mate score, e.g. '#-2', followed by the depth.
+pub fn evaluation_to_string(e: &Evaluation) -> String {
+	let mut s = match e.get_score() {
+		Score::Centipawns(cp) => {
+			let sign = if cp < 0 { "-" } else { "" };
+			let cp = cp.unsigned_abs();
+			let decimals = format!("{:02}", cp%100);
+			let decimals = if cp%10 == 0 { &decimals[..1] } else { &decimals[..] };
+			format!("{sign}{}.{decimals}", cp/100)
+		},
+		Score::MateIn(n) => format!("#{n}")
+	};
+	if let Some(depth) = e.get_depth() {
+		s.push(',');
+		s.push_str(&depth.to_string());
+	}
+	s
+}
+
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub enum MarkupColor { Green, Red, Yellow, Blue }
+
+fn classify_color(c: char) -> Option<MarkupColor> {
+	match c {
+		'G' => Some(MarkupColor::Green),
+		'R' => Some(MarkupColor::Red),
+		'Y' => Some(MarkupColor::Yellow),
+		'B' => Some(MarkupColor::Blue),
+		_ => None
+	}
+}
+
+fn color_to_char(c: &MarkupColor) -> char {
+	match c {
+		MarkupColor::Green => 'G',
+		MarkupColor::Red => 'R',
+		MarkupColor::Yellow => 'Y',
+		MarkupColor::Blue => 'B'
+	}
+}
+
+// An arrow drawn on the board, e.g. 'Gd2d4'.
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub struct Arrow {
+	m_color: MarkupColor,
+	m_from: board::Square,
+	m_to: board::Square
+}
+
+impl Arrow {
+	pub fn new(color: MarkupColor, from: board::Square, to: board::Square) -> Arrow {
+		Arrow {
+			m_color: color,
+			m_from: from,
+			m_to: to
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_color(&self) -> MarkupColor { self.m_color }
+	pub fn get_from(&self) -> board::Square { self.m_from }
+	pub fn get_to(&self) -> board::Square { self.m_to }
+}
+
+// A coloured square of the board, e.g. 'Rd4'.
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub struct Highlight {
+	m_color: MarkupColor,
+	m_square: board::Square
+}
+
+impl Highlight {
+	pub fn new(color: MarkupColor, square: board::Square) -> Highlight {
+		Highlight {
+			m_color: color,
+			m_square: square
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_color(&self) -> MarkupColor { self.m_color }
+	pub fn get_square(&self) -> board::Square { self.m_square }
+}
+
+// Parses a comma-separated list of arrows, e.g. 'Gd2d4,Re7e5'.
+pub fn parse_arrows(s: &str) -> Option<Vec<Arrow>> {
+	s.split(',')
+		.map(|a| {
+			let color = classify_color(a.chars().next()?)?;
+			let from = board::parse_square(a.get(1..3)?)?;
+			let to = board::parse_square(a.get(3..)?)?;
+			Some(Arrow::new(color, from, to))
+		})
+		.collect()
+}
+
+pub fn arrows_to_string(arrows: &[Arrow]) -> String {
+	arrows.iter()
+		.map(|a| format!("{}{}{}", color_to_char(&a.get_color()), a.get_from(), a.get_to()))
+		.collect::<Vec<String>>()
+		.join(",")
+}
+
+// Parses a comma-separated list of coloured squares, e.g. 'Rd4,Ge5'.
+pub fn parse_highlights(s: &str) -> Option<Vec<Highlight>> {
+	s.split(',')
+		.map(|h| {
+			let color = classify_color(h.chars().next()?)?;
+			let square = board::parse_square(h.get(1..)?)?;
+			Some(Highlight::new(color, square))
+		})
+		.collect()
+}
+
+pub fn highlights_to_string(highlights: &[Highlight]) -> String {
+	highlights.iter()
+		.map(|h| format!("{}{}", color_to_char(&h.get_color()), h.get_square()))
+		.collect::<Vec<String>>()
+		.join(",")
+}
+
+// How a comment is written: between curly brackets, or from a ';' to
+// the end of the line.
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub enum CommentStyle { Braces, RestOfLine }
+
+#[derive(Debug,Eq)]
 pub struct Comment {
 	m_text: String,
-	m_tags: Vec<(TagType, String)>
+	m_tags: Vec<(TagType, String)>,
+	m_raw_text: String,
+	m_style: CommentStyle
+}
+
+impl Default for Comment {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Comment {
 	pub fn new() -> Comment {
 		Comment {
 			m_text: String::new(),
-			m_tags: Vec::new()
+			m_tags: Vec::new(),
+			m_raw_text: String::new(),
+			m_style: CommentStyle::Braces
 		}
 	}
 	pub fn new_data(text: String, tags: Vec<(TagType,String)>) -> Comment {
 		Comment {
 			m_text: text,
-			m_tags: tags
+			m_tags: tags,
+			m_raw_text: String::new(),
+			m_style: CommentStyle::Braces
 		}
 	}
 	
@@ -75,6 +370,34 @@ impl Comment {
 	
 	pub fn get_text(&self) -> &String { &self.m_text }
 	pub fn get_tags(&self) -> &Vec<(TagType, String)> { &self.m_tags }
+	// The text between the curly brackets exactly as it appears in the
+	// source, with its tags, whitespace and line breaks.
+	pub fn get_raw_text(&self) -> &String { &self.m_raw_text }
+	pub fn get_style(&self) -> CommentStyle { self.m_style }
+	// The value of the first tag of type 't'.
+	pub fn get_tag(&self, t: &TagType) -> Option<&String> {
+		self.m_tags.iter().find(|(tag, _)| tag == t).map(|(_, value)| value)
+	}
+	// The clock of the player after the move ('%clk').
+	pub fn get_clock(&self) -> Option<Duration> {
+		parse_duration(self.get_tag(&TagType::Clock)?)
+	}
+	// The evaluation of the position after the move ('%eval').
+	pub fn get_evaluation(&self) -> Option<Evaluation> {
+		parse_evaluation(self.get_tag(&TagType::Eval)?)
+	}
+	// The arrows drawn on the board ('%cal').
+	pub fn get_arrows(&self) -> Option<Vec<Arrow>> {
+		parse_arrows(self.get_tag(&TagType::Arrows)?)
+	}
+	// The coloured squares of the board ('%csl').
+	pub fn get_highlights(&self) -> Option<Vec<Highlight>> {
+		parse_highlights(self.get_tag(&TagType::Highlights)?)
+	}
+	// The time spent on the move ('%emt').
+	pub fn get_elapsed_move_time(&self) -> Option<Duration> {
+		parse_duration(self.get_tag(&TagType::ElapsedMoveTime)?)
+	}
 	
 	/* MODIFIERS */
 	
@@ -84,4 +407,131 @@ impl Comment {
 	pub fn add_tag(&mut self, tag_name: TagType, tag_text: String) {
 		self.m_tags.push((tag_name, tag_text));
 	}
+	pub fn set_raw_text(&mut self, text: String) {
+		self.m_raw_text = text;
+	}
+	pub fn set_style(&mut self, style: CommentStyle) {
+		self.m_style = style;
+	}
+	// Replaces the value of the first tag of type 't', or adds the tag
+	// if the comment does not have it.
+	pub fn set_tag(&mut self, t: TagType, value: String) {
+		match self.m_tags.iter_mut().find(|(tag, _)| *tag == t) {
+			Some((_, v)) => *v = value,
+			None => self.m_tags.push((t, value))
+		}
+	}
+	pub fn set_clock(&mut self, d: &Duration) {
+		self.set_tag(TagType::Clock, duration_to_string(d));
+	}
+	pub fn set_elapsed_move_time(&mut self, d: &Duration) {
+		self.set_tag(TagType::ElapsedMoveTime, duration_to_string(d));
+	}
+	pub fn set_evaluation(&mut self, e: &Evaluation) {
+		self.set_tag(TagType::Eval, evaluation_to_string(e));
+	}
+	pub fn set_arrows(&mut self, arrows: &[Arrow]) {
+		self.set_tag(TagType::Arrows, arrows_to_string(arrows));
+	}
+	pub fn set_highlights(&mut self, highlights: &[Highlight]) {
+		self.set_tag(TagType::Highlights, highlights_to_string(highlights));
+	}
+}
+
+// Neither the raw text nor the style are taken into account: two comments
+// are equal when they have the same normalised text and the same tags.
+impl PartialEq for Comment {
+	fn eq(&self, other: &Self) -> bool {
+		self.m_text == other.m_text && self.m_tags == other.m_tags
+	}
+}
+
+// Splits the text of a comment at whitespace. Square brackets are words
+// on their own.
+fn comment_words(text: &str) -> Vec<&str> {
+	let mut words = Vec::new();
+	let mut start: Option<usize> = None;
+	for (k, c) in text.char_indices() {
+		if c.is_whitespace() || c == '[' || c == ']' {
+			if let Some(st) = start.take() {
+				words.push(&text[st..k]);
+			}
+			if c == '[' || c == ']' {
+				words.push(&text[k..k + 1]);
+			}
+		}
+		else if start.is_none() {
+			start = Some(k);
+		}
+	}
+	if let Some(st) = start {
+		words.push(&text[st..]);
+	}
+	words
+}
+
+// Reads the name and the value of a tag whose opening bracket is at
+// position 'j - 1'. Returns None if the tag is never closed.
+fn parse_comment_tag(words: &[&str], mut j: usize) -> Option<(usize, String, String)> {
+	let tag_name = words.get(j)?.to_string();
+	j += 1;
+
+	let mut text_tag = String::new();
+	loop {
+		match *words.get(j)? {
+			"]" => {
+				j += 1;
+				break;
+			},
+			"[" => {
+				j += 1;
+			},
+			w => {
+				text_tag.push_str(w);
+				j += 1;
+			}
+		}
+	}
+
+	Some((j, tag_name, text_tag))
+}
+
+// Parses the body of a comment, the text between the curly brackets or
+// after the semicolon, into its text and its tags. Returns None if a tag is not terminated.
+pub fn parse_comment_text(raw_text: String) -> Option<Comment> {
+	let mut com = Comment::new();
+
+	let words = comment_words(&raw_text);
+	let mut text_comment = String::new();
+	let mut j = 0;
+	while j < words.len() {
+		match words[j] {
+			"[" => {
+				let (next, tag_name, tag_text) = parse_comment_tag(&words, j + 1)?;
+				j = next;
+				com.add_tag(classify_tag(tag_name), tag_text);
+			},
+			"]" => {
+				j += 1;
+			},
+			w => {
+				if !text_comment.is_empty() {
+					text_comment.push(' ');
+				}
+				text_comment.push_str(w);
+				j += 1;
+			}
+		}
+	}
+	com.set_text(text_comment);
+	com.set_raw_text(raw_text);
+	Some(com)
+}
+
+// The comment whose text is the whole of 'raw_text', without any tags.
+pub fn comment_from_plain_text(raw_text: String) -> Comment {
+	let mut com = Comment::new();
+	com.set_text(raw_text.split_whitespace().collect::<Vec<_>>().join(" "));
+	com.set_raw_text(raw_text);
+	com
 }
@@ -30,21 +30,40 @@
  *
  ********************************************************************/
 
+use crate::board;
 use crate::comment;
+use crate::game_cursor;
+use crate::nag;
+use crate::pgn_error;
 use crate::pgn_tokenizer;
 
 #[repr(C)]
-#[derive(Debug,Eq,PartialEq)]
+#[derive(Debug,Eq)]
 pub struct GameTree {
 	m_game_move: String,
 	m_next: Option<Box<GameTree>>,
 	m_variations: Vec<GameTree>,
 
 	m_comments: Vec<comment::Comment>,
+	// comments before the move, only at the start of a line
+	m_pre_comments: Vec<comment::Comment>,
+	m_nags: Vec<nag::Nag>,
 	m_move_number: u16,
 
 	m_side: Option<pgn_tokenizer::Side>,
 	m_is_result: bool,
+	m_is_null_move: bool,
+	m_result: Option<pgn_tokenizer::ResultType>,
+
+	m_span: Option<pgn_tokenizer::Span>,
+	m_resolved_move: Option<board::Move>,
+	m_position: Option<board::Position>,
+}
+
+impl Default for GameTree {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl GameTree {
@@ -52,9 +71,16 @@ impl GameTree {
 		GameTree {
 			m_game_move: "".to_string(),
 			m_is_result: false,
+			m_is_null_move: false,
+			m_result: None,
 			m_move_number: 0,
 			m_side: None,
 			m_comments: Vec::new(),
+			m_pre_comments: Vec::new(),
+			m_nags: Vec::new(),
+			m_span: None,
+			m_resolved_move: None,
+			m_position: None,
 			
 			m_next: None,
 			m_variations: Vec::new(),
@@ -71,12 +97,21 @@ impl GameTree {
 	)
 	-> GameTree
 	{
+		let result = if is_result { pgn_tokenizer::classify_result(&game_move) } else { None };
+		let is_null_move = !is_result && pgn_tokenizer::is_null_move(&game_move);
 		GameTree {
 			m_game_move: game_move,
 			m_is_result: is_result,
+			m_is_null_move: is_null_move,
+			m_result: result,
 			m_move_number: move_number,
 			m_side: side,
 			m_comments: comments,
+			m_pre_comments: Vec::new(),
+			m_nags: Vec::new(),
+			m_span: None,
+			m_resolved_move: None,
+			m_position: None,
 			
 			m_next: main_line_next,
 			m_variations: variations,
@@ -88,14 +123,17 @@ impl GameTree {
 	pub fn set_move_text
 	(&mut self, text: String, s: &pgn_tokenizer::Side, num: u16)
 	{
+		self.m_is_null_move = pgn_tokenizer::is_null_move(&text);
 		self.m_game_move = text;
-		self.m_side = Some(s.clone());
+		self.m_side = Some(*s);
 		self.m_move_number = num;
 	}
 
 	pub fn set_result(&mut self, text: String) {
 		self.m_game_move = text;
 		self.m_is_result = true;
+		self.m_is_null_move = false;
+		self.m_result = pgn_tokenizer::classify_result(&self.m_game_move);
 		self.m_side = None;
 		self.m_move_number = 0;
 	}
@@ -111,6 +149,181 @@ impl GameTree {
 	pub fn add_comment(&mut self, comment: comment::Comment) {
 		self.m_comments.push(comment);
 	}
+
+	// Adds a comment that comes before the move. Only the first move of
+	// a line, of the game or of a variation, has these comments.
+	pub fn add_pre_comment(&mut self, comment: comment::Comment) {
+		self.m_pre_comments.push(comment);
+	}
+	pub fn add_nag(&mut self, nag: nag::Nag) {
+		self.m_nags.push(nag);
+	}
+
+	pub fn set_span(&mut self, span: pgn_tokenizer::Span) {
+		self.m_span = Some(span);
+	}
+
+	// The n-th variation replaces this move, which becomes the n-th
+	// variation in its place.
+	pub fn promote_variation(&mut self, n: usize) -> bool {
+		if n >= self.m_variations.len() {
+			return false;
+		}
+		let mut promoted = self.m_variations.remove(n);
+		std::mem::swap(self, &mut promoted);
+		// the comments before the first move of each line stay there
+		std::mem::swap(&mut self.m_pre_comments, &mut promoted.m_pre_comments);
+
+		// 'promoted' is now the former main move; the termination marker
+		// stays at the end of the main line
+		if let Some(result) = promoted.take_termination() {
+			let mut last = &mut *self;
+			while last.m_next.is_some() {
+				last = last.m_next.as_deref_mut().unwrap();
+			}
+			last.m_next = Some(Box::new(result));
+		}
+		let mut variations = std::mem::take(&mut promoted.m_variations);
+		variations.insert(n, promoted);
+		variations.append(&mut self.m_variations);
+		self.m_variations = variations;
+		true
+	}
+
+	// Removes the termination marker at the end of this line, if any.
+	fn take_termination(&mut self) -> Option<GameTree> {
+		let mut node = self;
+		while node.m_next.as_ref().is_some_and(|next| !next.m_is_result) {
+			node = node.m_next.as_deref_mut().unwrap();
+		}
+		node.m_next.take().map(|next| *next)
+	}
+
+	pub fn remove_variation(&mut self, n: usize) -> Option<GameTree> {
+		if n >= self.m_variations.len() {
+			return None;
+		}
+		Some(self.m_variations.remove(n))
+	}
+
+	pub fn insert_variation(&mut self, n: usize, variation: GameTree) -> bool {
+		if n > self.m_variations.len() {
+			return false;
+		}
+		self.m_variations.insert(n, variation);
+		true
+	}
+
+	// Promotes every variation on the way to the node at the path, so that
+	// the node ends up in the main line. Returns the new path to the node.
+	pub fn promote_to_main_line(&mut self, path: &game_cursor::NodePath) -> Option<game_cursor::NodePath> {
+		self.get_node(path)?;
+
+		let mut ply = path.get_main_line_ply();
+		for (variation, ply_in_variation) in path.get_variations().iter() {
+			let node = self.get_node_mut(&game_cursor::NodePath::from_main_line(ply))?;
+			node.promote_variation(*variation);
+			ply += ply_in_variation;
+		}
+		Some(game_cursor::NodePath::from_main_line(ply))
+	}
+
+	// Removes all the moves after this one, the termination marker
+	// included, and returns them.
+	pub fn truncate(&mut self) -> Option<GameTree> {
+		self.m_next.take().map(|next| *next)
+	}
+
+	// Inserts a move right after this one. The move that followed becomes
+	// a variation of the new move, along with its own variations, while
+	// the termination marker stays at the end of the line. None when the
+	// new move would be numbered past the last move number.
+	pub fn insert_move_after(&mut self, text: String) -> Option<&mut GameTree> {
+		let side = pgn_tokenizer::other_side(&self.m_side.unwrap_or(pgn_tokenizer::Side::Black));
+		let move_number =
+			if side == pgn_tokenizer::Side::White {
+				self.m_move_number.checked_add(1)?
+			}
+			else {
+				self.m_move_number
+			};
+
+		let mut g = GameTree::new();
+		g.set_move_text(text, &side, move_number);
+		match self.m_next.take() {
+			Some(next) if next.m_is_result => g.m_next = Some(next),
+			Some(mut next) => {
+				g.m_next = next.take_termination().map(Box::new);
+				g.m_variations = std::mem::take(&mut next.m_variations);
+				g.m_variations.insert(0, *next);
+			},
+			None => ()
+		}
+
+		self.m_next = Some(Box::new(g));
+		self.m_next.as_deref_mut()
+	}
+
+	// Replays the moves of this line and of all its variations starting at
+	// the position given. Every move that can be replayed is resolved into
+	// a concrete move. A line is no longer replayed after its first
+	// erroneous move. The errors come in the order of the moves in the text.
+	pub fn replay(&mut self, start: &board::Position) -> Vec<board::MoveError> {
+		let mut errors = Vec::new();
+
+		// moves still to replay, with the position before each of them
+		let mut pending = vec![(self, start.clone())];
+		while let Some((node, mut position)) = pending.pop() {
+			if node.m_is_result {
+				continue;
+			}
+			let before = position.clone();
+
+			// a null move only passes the turn
+			let replayed =
+				if node.m_is_null_move {
+					position.play_null_move();
+					node.m_resolved_move = None;
+					node.m_position = Some(position.clone());
+					true
+				}
+				else {
+					match position.parse_san(&node.m_game_move) {
+						Ok(m) => {
+							position.play(&m);
+							node.m_resolved_move = Some(m);
+							node.m_position = Some(position.clone());
+							true
+						},
+						Err(kind) => {
+							node.m_resolved_move = None;
+							node.m_position = None;
+							errors.push(board::MoveError::new(
+								kind,
+								node.m_move_number,
+								node.m_side.unwrap_or(position.get_side_to_move()),
+								node.m_game_move.clone(),
+								node.m_span
+							));
+							false
+						}
+					}
+				};
+
+			// the variations, alternatives to this move, are replayed before
+			// the rest of the line
+			let GameTree { m_variations, m_next, .. } = node;
+			if replayed {
+				if let Some(next) = m_next.as_deref_mut() {
+					pending.push((next, position));
+				}
+			}
+			for var in m_variations.iter_mut().rev() {
+				pending.push((var, before.clone()));
+			}
+		}
+		errors
+	}
 	
 	/* GETTERS */
 	
@@ -126,24 +339,117 @@ impl GameTree {
 
 	//pub fn is_move_empty(&self) -> bool { !self.is_result() && self.m_game_move == "".to_string() }
 	pub fn is_result(&self) -> bool { self.m_is_result }
+	// Whether the move is a null move, '--' or 'Z0'.
+	pub fn is_null_move(&self) -> bool { self.m_is_null_move }
+	// The result of the game, when this node is its termination marker.
+	pub fn get_result(&self) -> Option<pgn_tokenizer::ResultType> { self.m_result }
 	pub fn get_variations(&self) -> &Vec<GameTree> { &self.m_variations }
 	pub fn get_comments(&self) -> &Vec<comment::Comment> { &self.m_comments }
+	pub fn get_pre_comments(&self) -> &Vec<comment::Comment> { &self.m_pre_comments }
+	pub fn get_nags(&self) -> &Vec<nag::Nag> { &self.m_nags }
+	// The span of the move (or result) in the source it was parsed from.
+	pub fn get_span(&self) -> &Option<pgn_tokenizer::Span> { &self.m_span }
+	// The move resolved by the last replay of the game, if any.
+	pub fn get_resolved_move(&self) -> &Option<board::Move> { &self.m_resolved_move }
+	// The position after this move, as computed by the last replay of the game.
+	pub fn get_position(&self) -> &Option<board::Position> { &self.m_position }
+	// The node at the path, where the path starts at this node.
+	pub fn get_node(&self, path: &game_cursor::NodePath) -> Option<&GameTree> {
+		let mut node = self;
+		for _ in 0..path.get_main_line_ply() {
+			node = node.m_next.as_deref()?;
+		}
+		for (variation, ply) in path.get_variations().iter() {
+			node = node.m_variations.get(*variation)?;
+			for _ in 0..*ply {
+				node = node.m_next.as_deref()?;
+			}
+		}
+		Some(node)
+	}
+	pub fn get_node_mut(&mut self, path: &game_cursor::NodePath) -> Option<&mut GameTree> {
+		let mut node = self;
+		for _ in 0..path.get_main_line_ply() {
+			node = node.m_next.as_deref_mut()?;
+		}
+		for (variation, ply) in path.get_variations().iter() {
+			node = node.m_variations.get_mut(*variation)?;
+			for _ in 0..*ply {
+				node = node.m_next.as_deref_mut()?;
+			}
+		}
+		Some(node)
+	}
+	pub fn get_fen(&self) -> Option<String> {
+		self.m_position.as_ref().map(|p| p.to_fen())
+	}
+	// The clock of the player after this move, from the first comment
+	// of the move that has one.
+	pub fn get_clock(&self) -> Option<std::time::Duration> {
+		self.m_comments.iter().find_map(|c| c.get_clock())
+	}
+	// The evaluation of the position after this move, from the first
+	// comment of the move that has one.
+	pub fn get_evaluation(&self) -> Option<comment::Evaluation> {
+		self.m_comments.iter().find_map(|c| c.get_evaluation())
+	}
+	// The time spent on this move, from the first comment of the move
+	// that has one.
+	pub fn get_elapsed_move_time(&self) -> Option<std::time::Duration> {
+		self.m_comments.iter().find_map(|c| c.get_elapsed_move_time())
+	}
 	
 }
 
+// The span of a move and the result of replaying it are not taken into
+// account: two trees are equal when they contain the same moves, comments
+// and variations.
+impl PartialEq for GameTree {
+	fn eq(&self, other: &Self) -> bool {
+		let mut pending = vec![(self, other)];
+		while let Some((a, b)) = pending.pop() {
+			if
+				a.m_game_move != b.m_game_move ||
+				a.m_is_result != b.m_is_result ||
+				a.m_move_number != b.m_move_number ||
+				a.m_side != b.m_side ||
+				a.m_comments != b.m_comments ||
+				a.m_pre_comments != b.m_pre_comments ||
+				a.m_nags != b.m_nags ||
+				a.m_variations.len() != b.m_variations.len()
+			{
+				return false;
+			}
+
+			pending.extend(a.m_variations.iter().zip(b.m_variations.iter()));
+			match (&a.m_next, &b.m_next) {
+				(Some(next_a), Some(next_b)) => pending.push((next_a, next_b)),
+				(None, None) => { },
+				_ => return false
+			}
+		}
+		true
+	}
+}
+
 impl Drop for GameTree {
 	fn drop(&mut self) {
-		let mut next_game = self.m_next.take();
-		while let Some(mut game) = next_game {
-			next_game = game.m_next.take();
+		// Detach all the moves of the tree so that each one is dropped
+		// without any move left in it.
+		let mut pending: Vec<GameTree> = std::mem::take(&mut self.m_variations);
+		if let Some(next) = self.m_next.take() {
+			pending.push(*next);
+		}
+		while let Some(mut game) = pending.pop() {
+			pending.append(&mut game.m_variations);
+			if let Some(next) = game.m_next.take() {
+				pending.push(*next);
+			}
 		}
-
-		// No need to drop m_variations since this is handled automatically
-		// by Rust. The deallocation of each element in m_variations is handled
-		// by this function.
 	}
 }
 
+#[derive(Debug,Eq,PartialEq,Clone)]
 pub enum TagType {
 	Event,
 	Site,
@@ -165,43 +471,129 @@ pub enum TagType {
 	Variant,
 	ECO,
 	Opening,
+	SetUp,
+	FEN,
 	Other(String)
 }
 
 pub fn classify(s: String) -> TagType {
-	if s == "Event".to_string() { return TagType::Event; }
-	if s == "Site".to_string() { return TagType::Site; }
-	if s == "Date".to_string() { return TagType::Date; }
-	if s == "Round".to_string() { return TagType::Round; }
-	if s == "White".to_string() { return TagType::White; }
-	if s == "Black".to_string() { return TagType::Black; }
-	if s == "Result".to_string() { return TagType::Result; }
-	if s == "WhiteElo".to_string() { return TagType::WhiteElo; }
-	if s == "WhiteTeam".to_string() { return TagType::WhiteTeam; }
-	if s == "WhiteTitle".to_string() { return TagType::WhiteTitle; }
-	if s == "BlackElo".to_string() { return TagType::BlackElo; }
-	if s == "BlackTeam".to_string() { return TagType::BlackTeam; }
-	if s == "BlackTitle".to_string() { return TagType::BlackTitle; }
-	if s == "TimeControl".to_string() { return TagType::TimeControl; }
-	if s == "Termination".to_string() { return TagType::Termination; }
-	if s == "Board".to_string() { return TagType::Board; }
-	if s == "Annotator".to_string() { return TagType::Annotator; }
-	if s == "Variant".to_string() { return TagType::Variant; }
-	if s == "ECO".to_string() { return TagType::ECO; }
-	if s == "Opening".to_string() { return TagType::Opening; }
-	return TagType::Other(s);
+	if s == "Event" { return TagType::Event; }
+	if s == "Site" { return TagType::Site; }
+	if s == "Date" { return TagType::Date; }
+	if s == "Round" { return TagType::Round; }
+	if s == "White" { return TagType::White; }
+	if s == "Black" { return TagType::Black; }
+	if s == "Result" { return TagType::Result; }
+	if s == "WhiteElo" { return TagType::WhiteElo; }
+	if s == "WhiteTeam" { return TagType::WhiteTeam; }
+	if s == "WhiteTitle" { return TagType::WhiteTitle; }
+	if s == "BlackElo" { return TagType::BlackElo; }
+	if s == "BlackTeam" { return TagType::BlackTeam; }
+	if s == "BlackTitle" { return TagType::BlackTitle; }
+	if s == "TimeControl" { return TagType::TimeControl; }
+	if s == "Termination" { return TagType::Termination; }
+	if s == "Board" { return TagType::Board; }
+	if s == "Annotator" { return TagType::Annotator; }
+	if s == "Variant" { return TagType::Variant; }
+	if s == "ECO" { return TagType::ECO; }
+	if s == "Opening" { return TagType::Opening; }
+	if s == "SetUp" { return TagType::SetUp; }
+	if s == "FEN" { return TagType::FEN; }
+	TagType::Other(s)
+}
+
+pub fn tag_to_string(t: &TagType) -> String {
+	match t {
+		TagType::Event => "Event".to_string(),
+		TagType::Site => "Site".to_string(),
+		TagType::Date => "Date".to_string(),
+		TagType::Round => "Round".to_string(),
+		TagType::White => "White".to_string(),
+		TagType::Black => "Black".to_string(),
+		TagType::Result => "Result".to_string(),
+		TagType::WhiteElo => "WhiteElo".to_string(),
+		TagType::WhiteTeam => "WhiteTeam".to_string(),
+		TagType::WhiteTitle => "WhiteTitle".to_string(),
+		TagType::BlackElo => "BlackElo".to_string(),
+		TagType::BlackTeam => "BlackTeam".to_string(),
+		TagType::BlackTitle => "BlackTitle".to_string(),
+		TagType::TimeControl => "TimeControl".to_string(),
+		TagType::Termination => "Termination".to_string(),
+		TagType::Board => "Board".to_string(),
+		TagType::Annotator => "Annotator".to_string(),
+		TagType::Variant => "Variant".to_string(),
+		TagType::ECO => "ECO".to_string(),
+		TagType::Opening => "Opening".to_string(),
+		TagType::SetUp => "SetUp".to_string(),
+		TagType::FEN => "FEN".to_string(),
+		TagType::Other(s) => s.clone()
+	}
+}
+
+// The tags of the Seven Tag Roster, in the order of the standard.
+pub const SEVEN_TAG_ROSTER: [TagType; 7] = [
+	TagType::Event,
+	TagType::Site,
+	TagType::Date,
+	TagType::Round,
+	TagType::White,
+	TagType::Black,
+	TagType::Result
+];
+
+// The termination marker of a game disagrees with its 'Result' tag.
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub struct ResultMismatch {
+	m_tag_value: String,
+	m_terminator: pgn_tokenizer::ResultType
+}
+
+impl ResultMismatch {
+	pub fn new(tag_value: String, terminator: pgn_tokenizer::ResultType) -> ResultMismatch {
+		ResultMismatch {
+			m_tag_value: tag_value,
+			m_terminator: terminator
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_tag_value(&self) -> &String { &self.m_tag_value }
+	pub fn get_terminator(&self) -> pgn_tokenizer::ResultType { self.m_terminator }
+}
+
+impl std::fmt::Display for ResultMismatch {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"The Result tag '{}' disagrees with the termination marker '{}'",
+			self.m_tag_value,
+			pgn_tokenizer::result_to_string(&self.m_terminator)
+		)
+	}
 }
 
 pub struct Game {
 	m_tree: GameTree,
-	m_tags: Vec<(TagType,String)>
+	m_tags: Vec<(TagType,String)>,
+	m_initial_position: board::Position,
+	// errors the parser recovered from while reading the game
+	m_warnings: Vec<pgn_error::PgnError>
+}
+
+impl Default for Game {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Game {
 	pub fn new() -> Game {
 		Game {
 			m_tree: GameTree::new(),
-			m_tags: Vec::new()
+			m_tags: Vec::new(),
+			m_initial_position: board::Position::new(),
+			m_warnings: Vec::new()
 		}
 	}
 
@@ -212,6 +604,29 @@ impl Game {
 	pub fn add_game_tag(&mut self, tag: (TagType, String)) {
 		self.m_tags.push(tag);
 	}
+	pub fn add_warning(&mut self, warning: pgn_error::PgnError) {
+		self.m_warnings.push(warning);
+	}
+	// Replaces the value of the first tag of type 't', or adds the tag
+	// if the game does not have it.
+	pub fn set_tag(&mut self, t: TagType, value: String) {
+		match self.m_tags.iter_mut().find(|(tag, _)| *tag == t) {
+			Some((_, v)) => *v = value,
+			None => self.m_tags.push((t, value))
+		}
+	}
+	// Removes all the tags of type 't' and returns the value of the first.
+	pub fn remove_tag(&mut self, t: &TagType) -> Option<String> {
+		let first = self.m_tags.iter().position(|(tag, _)| tag == t)?;
+		let (_, value) = self.m_tags.remove(first);
+		self.m_tags.retain(|(tag, _)| tag != t);
+		Some(value)
+	}
+
+	// The position before the first move, given by the 'FEN' tag.
+	pub fn set_initial_position(&mut self, p: board::Position) {
+		self.m_initial_position = p;
+	}
 
 	pub fn get_tree(&self) -> &GameTree {
 		&self.m_tree
@@ -219,4 +634,59 @@ impl Game {
 	pub fn get_tree_mut(&mut self) -> &mut GameTree {
 		&mut self.m_tree
 	}
+	pub fn get_initial_position(&self) -> &board::Position {
+		&self.m_initial_position
+	}
+	// The errors found in the movetext that did not stop the parser, when
+	// parsing in lenient mode.
+	pub fn get_warnings(&self) -> &Vec<pgn_error::PgnError> {
+		&self.m_warnings
+	}
+
+	// All the tags in the order in which they were added.
+	pub fn get_tags(&self) -> &Vec<(TagType,String)> {
+		&self.m_tags
+	}
+	// The value of the first tag of type 't'.
+	pub fn get_tag(&self, t: &TagType) -> Option<&String> {
+		self.m_tags.iter().find(|(tag, _)| tag == t).map(|(_, value)| value)
+	}
+	pub fn get_event(&self) -> Option<&String> { self.get_tag(&TagType::Event) }
+	pub fn get_site(&self) -> Option<&String> { self.get_tag(&TagType::Site) }
+	pub fn get_date(&self) -> Option<&String> { self.get_tag(&TagType::Date) }
+	pub fn get_round(&self) -> Option<&String> { self.get_tag(&TagType::Round) }
+	pub fn get_white(&self) -> Option<&String> { self.get_tag(&TagType::White) }
+	pub fn get_black(&self) -> Option<&String> { self.get_tag(&TagType::Black) }
+	pub fn get_result(&self) -> Option<&String> { self.get_tag(&TagType::Result) }
+
+	// The result given by the termination marker of the movetext. A game
+	// without termination marker has an unknown result.
+	pub fn result(&self) -> pgn_tokenizer::ResultType {
+		let mut last = &self.m_tree;
+		while let Some(next) = last.get_next_move() {
+			last = next;
+		}
+		last.get_result().unwrap_or(pgn_tokenizer::ResultType::Unknown)
+	}
+
+	// Checks that the 'Result' tag, if any, agrees with the termination
+	// marker of the movetext.
+	pub fn check_result(&self) -> Result<(), ResultMismatch> {
+		let Some(value) = self.get_result() else {
+			return Ok(());
+		};
+		let terminator = self.result();
+		if pgn_tokenizer::classify_result(value) != Some(terminator) {
+			return Err(ResultMismatch::new(value.to_string(), terminator));
+		}
+		Ok(())
+	}
+
+	// Replays all the moves of the game from its initial position.
+	pub fn replay_moves(&mut self) -> Vec<board::MoveError> {
+		if self.m_tree.m_game_move.is_empty() {
+			return Vec::new();
+		}
+		self.m_tree.replay(&self.m_initial_position)
+	}
 }
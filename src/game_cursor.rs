@@ -0,0 +1,232 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+use crate::game;
+
+// The location of a node of a game tree. The node is reached by moving
+// forward along the main line a number of plies, then, for every pair
+// (variation, ply), entering that variation of the node reached so far
+// and moving forward along it that number of plies.
+#[derive(Debug,Eq,PartialEq,Clone,Hash,Default)]
+pub struct NodePath {
+	m_main_line_ply: usize,
+	m_variations: Vec<(usize, usize)>
+}
+
+impl NodePath {
+	// The path to the first move of the game.
+	pub fn new() -> NodePath {
+		NodePath {
+			m_main_line_ply: 0,
+			m_variations: Vec::new()
+		}
+	}
+	pub fn from_main_line(ply: usize) -> NodePath {
+		NodePath {
+			m_main_line_ply: ply,
+			m_variations: Vec::new()
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_main_line_ply(&self) -> usize { self.m_main_line_ply }
+	pub fn get_variations(&self) -> &Vec<(usize, usize)> { &self.m_variations }
+	// Number of plies played before the move at the path.
+	pub fn get_ply(&self) -> usize {
+		self.m_main_line_ply + self.m_variations.iter().map(|(_, ply)| ply).sum::<usize>()
+	}
+	pub fn is_main_line(&self) -> bool { self.m_variations.is_empty() }
+
+	/* MODIFIERS */
+
+	// Enters the variation of the node and moves 'ply' plies along it.
+	pub fn push_variation(&mut self, variation: usize, ply: usize) {
+		self.m_variations.push((variation, ply));
+	}
+	// Leaves the innermost variation and returns its index and ply.
+	pub fn pop_variation(&mut self) -> Option<(usize, usize)> {
+		self.m_variations.pop()
+	}
+	// Moves the node 'ply' plies along its line.
+	pub fn set_ply_in_line(&mut self, ply: usize) {
+		match self.m_variations.last_mut() {
+			Some((_, p)) => *p = ply,
+			None => self.m_main_line_ply = ply
+		}
+	}
+	pub fn get_ply_in_line(&self) -> usize {
+		match self.m_variations.last() {
+			Some((_, p)) => *p,
+			None => self.m_main_line_ply
+		}
+	}
+}
+
+// A path is written as the ply in the main line followed by a '/' and
+// the pair 'variation.ply' for every variation, e.g. '12/2.3'.
+impl std::fmt::Display for NodePath {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}", self.m_main_line_ply)?;
+		for (variation, ply) in self.m_variations.iter() {
+			write!(f, "/{variation}.{ply}")?;
+		}
+		Ok(())
+	}
+}
+
+pub fn parse_node_path(s: &str) -> Option<NodePath> {
+	let mut fields = s.split('/');
+	let mut path = NodePath::from_main_line(fields.next()?.parse::<usize>().ok()?);
+	for field in fields {
+		let (variation, ply) = field.split_once('.')?;
+		path.push_variation(variation.parse::<usize>().ok()?, ply.parse::<usize>().ok()?);
+	}
+	Some(path)
+}
+
+// Moves along the moves of a game tree. The cursor remembers the moves
+// it went through, so it can go back to them.
+pub struct GameCursor<'a> {
+	// the moves of every line from its first move to the current one; the
+	// first line is the main line
+	m_lines: Vec<Vec<&'a game::GameTree>>,
+	m_path: NodePath
+}
+
+impl<'a> GameCursor<'a> {
+	// A cursor at the first move of the tree.
+	pub fn new(tree: &'a game::GameTree) -> GameCursor<'a> {
+		GameCursor {
+			m_lines: vec![vec![tree]],
+			m_path: NodePath::new()
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_node(&self) -> &'a game::GameTree {
+		self.m_lines.last().unwrap().last().unwrap()
+	}
+	pub fn get_path(&self) -> &NodePath { &self.m_path }
+	pub fn get_ply(&self) -> usize { self.m_path.get_ply() }
+	pub fn is_in_variation(&self) -> bool { self.m_lines.len() > 1 }
+
+	/* MOVEMENT */
+
+	// Moves to the next move of the current line. Termination markers are
+	// not moves.
+	pub fn forward(&mut self) -> bool {
+		match self.get_node().get_next_move() {
+			Some(next) if !next.is_result() => {
+				self.m_lines.last_mut().unwrap().push(next);
+				self.m_path.set_ply_in_line(self.m_path.get_ply_in_line() + 1);
+				true
+			},
+			_ => false
+		}
+	}
+
+	// Moves to the previous move of the current line.
+	pub fn back(&mut self) -> bool {
+		let line = self.m_lines.last_mut().unwrap();
+		if line.len() == 1 {
+			return false;
+		}
+		line.pop();
+		self.m_path.set_ply_in_line(self.m_path.get_ply_in_line() - 1);
+		true
+	}
+
+	// Moves to the first move of the n-th variation of the current move.
+	pub fn enter_variation(&mut self, n: usize) -> bool {
+		match self.get_node().get_variations().get(n) {
+			Some(variation) => {
+				self.m_lines.push(vec![variation]);
+				self.m_path.push_variation(n, 0);
+				true
+			},
+			None => false
+		}
+	}
+
+	// Moves to the move the current variation is an alternative to.
+	pub fn exit_variation(&mut self) -> bool {
+		if self.m_lines.len() == 1 {
+			return false;
+		}
+		self.m_lines.pop();
+		self.m_path.pop_variation();
+		true
+	}
+
+	// Moves to the move at the given ply of the main line.
+	pub fn go_to_ply(&mut self, ply: usize) -> bool {
+		self.go_to(&NodePath::from_main_line(ply))
+	}
+
+	// Moves to the node at the path. The cursor does not move when the
+	// path does not lead to a move of the tree.
+	pub fn go_to(&mut self, path: &NodePath) -> bool {
+		let root = self.m_lines[0][0];
+		let mut lines = vec![vec![root]];
+
+		let walk = |line: &mut Vec<&'a game::GameTree>, ply: usize| -> bool {
+			for _ in 0..ply {
+				match line.last().unwrap().get_next_move() {
+					Some(next) if !next.is_result() => line.push(next),
+					_ => return false
+				}
+			}
+			true
+		};
+
+		if !walk(&mut lines[0], path.get_main_line_ply()) {
+			return false;
+		}
+		for (variation, ply) in path.get_variations().iter() {
+			let node = lines.last().unwrap().last().unwrap();
+			let Some(first) = node.get_variations().get(*variation) else {
+				return false;
+			};
+			let mut line = vec![first];
+			if !walk(&mut line, *ply) {
+				return false;
+			}
+			lines.push(line);
+		}
+
+		self.m_lines = lines;
+		self.m_path = path.clone();
+		true
+	}
+}
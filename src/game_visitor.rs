@@ -0,0 +1,253 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+use crate::comment;
+use crate::game;
+use crate::nag;
+use crate::pgn_tokenizer;
+use crate::pgn_tokenizer::{Side, TokenType};
+
+// Callbacks called while walking a game in document order. Every method
+// does nothing by default, so that a visitor only needs to implement the
+// ones it is interested in.
+pub trait GameVisitor {
+	fn begin_game(&mut self) { }
+	fn end_game(&mut self) { }
+
+	// The value is given without its quotes and escape sequences.
+	fn tag(&mut self, _tag: &game::TagType, _value: &str) { }
+
+	fn game_move(&mut self, _move_number: u16, _side: Side, _text: &str) { }
+	fn comment(&mut self, _comment: &comment::Comment) { }
+	fn nag(&mut self, _nag: &nag::Nag) { }
+
+	fn begin_variation(&mut self) { }
+	fn end_variation(&mut self) { }
+
+	fn result(&mut self, _result: pgn_tokenizer::ResultType) { }
+}
+
+enum Step<'a> {
+	Node(&'a game::GameTree),
+	BeginVariation,
+	EndVariation
+}
+
+// Walks a game that has already been built. After every move come its
+// glyphs, its comments, its variations and then the next move, the same
+// order in which the formatter writes the game. The comments before the
+// first move of a line come right before that move.
+pub fn visit_game<V: GameVisitor>(g: &game::Game, v: &mut V) {
+	v.begin_game();
+	for (t, value) in g.get_tags().iter() {
+		v.tag(t, value);
+	}
+
+	// the steps still to be taken, in reverse order
+	let mut pending = vec![Step::Node(g.get_tree())];
+	while let Some(step) = pending.pop() {
+		let node = match step {
+			Step::BeginVariation => {
+				v.begin_variation();
+				continue;
+			},
+			Step::EndVariation => {
+				v.end_variation();
+				continue;
+			},
+			Step::Node(node) => node
+		};
+
+		for c in node.get_pre_comments().iter() {
+			v.comment(c);
+		}
+		if node.is_result() {
+			v.result(node.get_result().unwrap_or(pgn_tokenizer::ResultType::Unknown));
+		}
+		else if let Some(side) = node.get_side() {
+			v.game_move(*node.get_move_number(), *side, node.get_move_text());
+		}
+		for n in node.get_nags().iter() {
+			v.nag(n);
+		}
+		for c in node.get_comments().iter() {
+			v.comment(c);
+		}
+
+		if let Some(next) = node.get_next_move() {
+			pending.push(Step::Node(next));
+		}
+		for var in node.get_variations().iter().rev() {
+			pending.push(Step::EndVariation);
+			pending.push(Step::Node(var));
+			pending.push(Step::BeginVariation);
+		}
+	}
+	v.end_game();
+}
+
+// Number and side of the move that follows the move of 'side'. None
+// after the last move number.
+fn advance(move_number: u16, side: Side) -> Option<(u16, Side)> {
+	match side {
+		Side::White => Some((move_number, Side::Black)),
+		Side::Black => Some((move_number.checked_add(1)?, Side::White))
+	}
+}
+
+// The comment with the text 'raw_text'. When its tags cannot be parsed,
+// the whole text is the text of the comment.
+fn make_comment(raw_text: String, style: comment::CommentStyle) -> comment::Comment {
+	let mut com = comment::parse_comment_text(raw_text.clone())
+		.unwrap_or_else(|| comment::comment_from_plain_text(raw_text));
+	com.set_style(style);
+	com
+}
+
+// Position of the moves within a line of the movetext.
+struct LineState {
+	m_next: Option<(u16, Side)>,
+	m_last: Option<(u16, Side)>
+}
+
+// Walks the games in a list of tokens without building their trees. The
+// callbacks are called in the order in which the elements appear in the
+// input. The tokens are not validated: malformed input produces whatever
+// callbacks its tokens suggest, except that the moves that would be
+// numbered past the last move number are not visited.
+pub fn visit_tokens<V: GameVisitor>(
+	tokens: &pgn_tokenizer::AllTokens,
+	token_types: &pgn_tokenizer::AllTokenTypes,
+	v: &mut V
+)
+{
+	assert_eq!(tokens.len(), token_types.len());
+
+	let mut in_game = false;
+	let mut in_movetext = false;
+	let first_move = Some((1, Side::White));
+	let mut lines = vec![LineState { m_next: first_move, m_last: first_move }];
+
+	let mut i = 0;
+	while i < tokens.len() {
+		if let TokenType::TagDelim { open: true } = &token_types[i] {
+			// a tag section after some movetext starts a new game
+			if in_movetext {
+				v.end_game();
+				in_game = false;
+			}
+		}
+		if !in_game {
+			v.begin_game();
+			in_game = true;
+			in_movetext = false;
+			lines = vec![LineState { m_next: first_move, m_last: first_move }];
+		}
+
+		match &token_types[i] {
+			TokenType::TagDelim { open: true } => {
+				if i + 2 < tokens.len() {
+					v.tag(
+						&game::classify(tokens[i + 1].clone()),
+						&pgn_tokenizer::unquote_string(&tokens[i + 2])
+					);
+				}
+				// skip to the closing bracket
+				while i < tokens.len() && token_types[i] != (TokenType::TagDelim { open: false }) {
+					i += 1;
+				}
+			},
+			TokenType::TagDelim { open: false } => { },
+
+			TokenType::CommentDelim { open: true } => {
+				in_movetext = true;
+				let mut raw_text = String::new();
+				if i + 1 < tokens.len() && token_types[i + 1] == TokenType::Text {
+					raw_text = tokens[i + 1].clone();
+					i += 1;
+				}
+				v.comment(&make_comment(raw_text, comment::CommentStyle::Braces));
+			},
+			TokenType::CommentDelim { open: false } => { },
+			TokenType::LineComment => {
+				in_movetext = true;
+				v.comment(&make_comment(tokens[i].clone(), comment::CommentStyle::RestOfLine));
+			},
+
+			TokenType::VariantDelim { open: true } => {
+				in_movetext = true;
+				// a variation is an alternative to the last move of its line
+				let last = lines.last().unwrap().m_last;
+				lines.push(LineState { m_next: last, m_last: last });
+				v.begin_variation();
+			},
+			TokenType::VariantDelim { open: false } => {
+				if lines.len() > 1 {
+					lines.pop();
+				}
+				v.end_variation();
+			},
+
+			TokenType::MoveNumber { id, side } => {
+				in_movetext = true;
+				lines.last_mut().unwrap().m_next = Some((*id, *side));
+			},
+			TokenType::Nag { value } => {
+				in_movetext = true;
+				v.nag(&nag::Nag::new(*value));
+			},
+			TokenType::Text | TokenType::NullMove => {
+				in_movetext = true;
+				let line = lines.last_mut().unwrap();
+				line.m_last = line.m_next;
+				if let Some((move_number, side)) = line.m_last {
+					v.game_move(move_number, side, &tokens[i]);
+					line.m_next = advance(move_number, side);
+				}
+			},
+
+			TokenType::Result { result } => {
+				v.result(*result);
+				// only the termination marker of the main line ends the game
+				if lines.len() == 1 {
+					v.end_game();
+					in_game = false;
+				}
+			}
+		}
+		i += 1;
+	}
+
+	if in_game {
+		v.end_game();
+	}
+}
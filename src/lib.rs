@@ -1,5 +1,11 @@
+pub mod board;
 pub mod comment;
 pub mod game;
+pub mod game_cursor;
+pub mod game_visitor;
+pub mod nag;
+pub mod pgn_error;
 pub mod pgn_formatter;
+pub mod pgn_reader;
 pub mod pgn_tree_builder;
 pub mod pgn_tokenizer;
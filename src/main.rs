@@ -31,31 +31,18 @@
  ********************************************************************/
 
 use std::env;
-use std::io::BufRead;
 
-mod comment;
-mod game;
-mod pgn_formatter;
-mod pgn_tokenizer;
-mod pgn_tree_builder;
+use pgn_parser::comment;
+use pgn_parser::game;
+use pgn_parser::pgn_formatter;
+use pgn_parser::pgn_reader;
 
 fn analyze_file(p: String) -> game::Game {
-	let mut entire_file_str = String::new();
-	
 	let file = std::fs::File::open(p).expect("Failed to open file");
-	let reader = std::io::BufReader::new(file);
-	for line in reader.lines() {
-		entire_file_str.push_str( line.unwrap().trim() );
-	}
-	
-	let (all_tokens, all_token_types) =
-		pgn_tokenizer::tokenize(entire_file_str);
-
-	let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
-	builder.set_token_list(all_tokens, all_token_types);
+	let mut reader = pgn_reader::PgnReader::new(std::io::BufReader::new(file));
 
 	/*
-	if let Some(game) = builder.build_game() {
+	if let Some(game) = reader.next() {
 		let res = pgn_formatter::PgnFormatter::new()
 			.set_print_comments(true)
 			.set_print_variation(true)
@@ -67,7 +54,9 @@ fn analyze_file(p: String) -> game::Game {
 	}
 	*/
 
-	builder.build_game().unwrap()
+	reader.next()
+		.expect("The file does not contain any game")
+		.expect("Failed to parse the game")
 }
 
 pub fn read_input_string() -> String {
@@ -79,7 +68,7 @@ pub fn read_input_string() -> String {
 
 pub fn read_string_or_empty() -> Option<String> {
 	let str = read_input_string();
-	if str == "".to_string() { return None; }
+	if str.is_empty() { return None; }
 	Some(str)
 }
 
@@ -107,23 +96,9 @@ fn main() {
 		}
 
 		if let Ok(_min) = _min_str.parse::<u32>() {
-			let hours = _min/60;
-			let minutes = _min%60;
-	
-			let clock =
-				hours.to_string() + ":" +
-				if minutes < 10 { "0" } else { "" } +
-				&minutes.to_string() +
-				":00";
-	
-			gt.add_comment(
-				comment::Comment::new_data(
-					"".to_string(),
-					vec![
-						(comment::TagType::Clock, clock)
-					]
-				)
-			);
+			let mut c = comment::Comment::new();
+			c.set_clock(&std::time::Duration::from_secs(u64::from(_min)*60));
+			gt.add_comment(c);
 	
 			if gt.has_next_move() {
 				gt = &mut *gt.get_next_move_mut().as_mut().unwrap();
@@ -145,6 +120,6 @@ fn main() {
 		.to_string(g.get_tree());
 
 	println!("{res}");
-	println!("");
+	println!();
 
 }
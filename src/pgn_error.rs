@@ -0,0 +1,118 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+use crate::pgn_tokenizer;
+
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub enum PgnErrorKind {
+	UnexpectedToken { token: String },
+	UnexpectedEndOfInput,
+	UnbalancedVariation,
+	MoveNumberMismatch {
+		expected_id: u16,
+		expected_side: pgn_tokenizer::Side,
+		found_id: u16,
+		found_side: pgn_tokenizer::Side
+	},
+	UnterminatedComment,
+	UnterminatedCommentTag,
+	MoveNumberOverflow,
+	InvalidFen { fen: String },
+	Io { message: String }
+}
+
+#[derive(Debug,Eq,PartialEq,Clone)]
+pub struct PgnError {
+	m_kind: PgnErrorKind,
+	m_token_index: usize,
+	m_span: Option<pgn_tokenizer::Span>
+}
+
+impl PgnError {
+	pub fn new(
+		kind: PgnErrorKind,
+		token_index: usize,
+		span: Option<pgn_tokenizer::Span>
+	)
+	-> PgnError
+	{
+		PgnError {
+			m_kind: kind,
+			m_token_index: token_index,
+			m_span: span
+		}
+	}
+
+	/* GETTERS */
+
+	pub fn get_kind(&self) -> &PgnErrorKind { &self.m_kind }
+	pub fn get_token_index(&self) -> usize { self.m_token_index }
+	// The span of the offending token. None when the error was found at the
+	// end of the input.
+	pub fn get_span(&self) -> &Option<pgn_tokenizer::Span> { &self.m_span }
+}
+
+impl std::fmt::Display for PgnError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match &self.m_kind {
+			PgnErrorKind::UnexpectedToken { token } =>
+				write!(f, "Unexpected token '{token}'")?,
+			PgnErrorKind::UnexpectedEndOfInput =>
+				write!(f, "Unexpected end of input")?,
+			PgnErrorKind::UnbalancedVariation =>
+				write!(f, "Unbalanced variation")?,
+			PgnErrorKind::MoveNumberMismatch { expected_id, expected_side, found_id, found_side } =>
+				write!(
+					f,
+					"Expected move number {expected_id} ({expected_side:?}) \
+					but found {found_id} ({found_side:?})"
+				)?,
+			PgnErrorKind::UnterminatedComment =>
+				write!(f, "Unterminated comment")?,
+			PgnErrorKind::UnterminatedCommentTag =>
+				write!(f, "Unterminated comment tag")?,
+			PgnErrorKind::MoveNumberOverflow =>
+				write!(f, "Move number too large")?,
+			PgnErrorKind::InvalidFen { fen } =>
+				write!(f, "Invalid FEN '{fen}'")?,
+			PgnErrorKind::Io { message } =>
+				write!(f, "Input error: {message}")?,
+		}
+		write!(f, " at token {}", self.m_token_index)?;
+		if let Some(span) = &self.m_span {
+			write!(f, " (line {}, column {})", span.get_line(), span.get_column())?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for PgnError {}
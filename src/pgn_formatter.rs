@@ -30,14 +30,30 @@
  *
  ********************************************************************/
 
- use crate::comment;
+use crate::comment;
 use crate::game;
 use crate::pgn_tokenizer;
 
+// A piece of text still to be written by the formatter.
+enum Pending<'a> {
+	Move(&'a game::GameTree, bool),
+	Text(&'static str)
+}
+
 pub struct PgnFormatter {
-    m_print_comments: bool,
-    m_print_variants: bool,
-    m_print_result: bool
+	m_print_comments: bool,
+	m_print_variants: bool,
+	m_print_result: bool,
+	m_print_nags_as_symbols: bool,
+	m_print_comments_verbatim: bool,
+	m_export_format: bool,
+	m_line_width: usize
+}
+
+impl Default for PgnFormatter {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl PgnFormatter {
@@ -47,6 +63,10 @@ impl PgnFormatter {
 			m_print_comments: true,
 			m_print_variants: true,
 			m_print_result: true,
+			m_print_nags_as_symbols: true,
+			m_print_comments_verbatim: false,
+			m_export_format: false,
+			m_line_width: 79
 		}
 	}
 
@@ -62,61 +82,197 @@ impl PgnFormatter {
 		self.m_print_result = v;
 		self
 	}
+	// When false, annotation glyphs are always printed as '$n'.
+	pub fn set_print_nags_as_symbols(&mut self, v: bool) -> &mut PgnFormatter {
+		self.m_print_nags_as_symbols = v;
+		self
+	}
+	// When true, comments are printed exactly as they were in the source,
+	// with their whitespace and line breaks.
+	pub fn set_print_comments_verbatim(&mut self, v: bool) -> &mut PgnFormatter {
+		self.m_print_comments_verbatim = v;
+		self
+	}
+	// In export format, the movetext is wrapped into lines of at most
+	// 'line width' characters, annotation glyphs are printed as '$n' and
+	// the result is always printed. A comment printed verbatim is not
+	// wrapped, so its lines may be longer.
+	pub fn set_export_format(&mut self, v: bool) -> &mut PgnFormatter {
+		self.m_export_format = v;
+		self
+	}
+	pub fn set_line_width(&mut self, w: usize) -> &mut PgnFormatter {
+		self.m_line_width = w;
+		self
+	}
 
-	fn to_string_rec(&self, g: &game::GameTree, show_move_number: bool, s: &mut String) {
-		if show_move_number {
-			if let Some(side) = g.get_side() {
-				s.push_str(&g.get_move_number().to_string());
-				if side == &pgn_tokenizer::Side::White {
-					s.push_str(". ");
+	fn prints_nags_as_symbols(&self) -> bool {
+		self.m_print_nags_as_symbols && !self.m_export_format
+	}
+	fn prints_result(&self) -> bool {
+		self.m_print_result || self.m_export_format
+	}
+
+	// Breaks the text into lines of at most 'line width' characters
+	// without splitting any token. A rest-of-line comment is always
+	// followed by a line break, and a comment printed verbatim is kept
+	// whole, even if it is longer than a line.
+	fn wrap(&self, text: &str) -> String {
+		let mut s = String::new();
+		let mut line_length = 0;
+		let mut break_line = false;
+		for (word, is_line_comment) in movetext_words(text, self.m_print_comments_verbatim) {
+			let word_length = word.chars().count();
+			if line_length > 0 {
+				if break_line || line_length + 1 + word_length > self.m_line_width {
+					s.push('\n');
+					line_length = 0;
 				}
 				else {
-					s.push_str("... ");
+					s.push(' ');
+					line_length += 1;
 				}
 			}
+			s.push_str(word);
+			line_length = match word.rfind('\n') {
+				Some(j) => word[j + 1..].chars().count(),
+				None => line_length + word_length
+			};
+			break_line = is_line_comment;
 		}
-		
-		s.push_str(g.get_move_text());
-		
-		let mut show_num_next_move = false;
-
-		if self.m_print_comments {
-			for c in g.get_comments().iter() {
-				show_num_next_move = true;
-				s.push_str(" { ");
-				
-				for tag in c.get_tags().iter() {
-					s.push_str("[");
-					s.push_str(&comment::tag_to_string(&tag.0));
-					s.push_str(" ");
-					s.push_str(&tag.1);
-					s.push_str("] ");
-				}
-				
-				s.push_str(c.get_text());
-				if c.get_text() != &"".to_string() {
-					s.push_str(" ");
+		s
+	}
+
+	fn nags_to_string(&self, g: &game::GameTree, s: &mut String) {
+		for (i, nag) in g.get_nags().iter().enumerate() {
+			match nag.get_symbol() {
+				Some(symbol) if self.prints_nags_as_symbols() => {
+					// only the first glyph can be attached to the move
+					if i > 0 || !nag.is_move_assessment() {
+						s.push(' ');
+					}
+					s.push_str(symbol);
+				},
+				_ => {
+					s.push(' ');
+					s.push_str(&nag.to_numeric_string());
 				}
-				s.push_str("}");
 			}
 		}
-		
-		if self.m_print_variants {
-			for var in g.get_variations().iter() {
-				show_num_next_move = true;
-				s.push_str(" (");
-				self.to_string_rec(&var, true, s);
-				s.push_str(")");
-			}
+	}
+
+	fn comment_to_string(&self, c: &comment::Comment, s: &mut String) {
+		if self.m_print_comments_verbatim && c.get_style() == comment::CommentStyle::RestOfLine {
+			s.push(';');
+			s.push_str(c.get_raw_text());
+			s.push('\n');
+			return;
+		}
+		if self.m_print_comments_verbatim && !c.get_raw_text().is_empty() {
+			s.push('{');
+			s.push_str(c.get_raw_text());
+			s.push('}');
+			return;
+		}
+
+		let mut body = String::new();
+		for tag in c.get_tags().iter() {
+			body.push('[');
+			body.push_str(&comment::tag_to_string(&tag.0));
+			body.push(' ');
+			body.push_str(&tag.1);
+			body.push_str("] ");
 		}
+		body.push_str(c.get_text());
+
+		// a '}' would end a comment in braces, but not a rest-of-line one
+		if body.contains('}') {
+			s.push_str("; ");
+			s.push_str(body.trim_end());
+			s.push('\n');
+			return;
+		}
+
+		s.push_str("{ ");
+		s.push_str(&body);
+		if !c.get_text().is_empty() {
+			s.push(' ');
+		}
+		s.push('}');
+	}
 
-		if let Some(res) = g.get_next_move() {
-			if !res.is_result() || self.m_print_result {
-				s.push_str(" ");
-				if let Some(next_side) = res.get_side() {
-					show_num_next_move = show_num_next_move || next_side == &pgn_tokenizer::Side::White;
+	// Appends the text of the line that starts at 'g'. Instead of recursion,
+	// the pieces of text still to be written are kept in a stack.
+	fn to_string_rec(&self, g: &game::GameTree, show_move_number: bool, s: &mut String) {
+		let mut pending = vec![Pending::Move(g, show_move_number)];
+
+		while let Some(p) = pending.pop() {
+			let (g, show_move_number) = match p {
+				Pending::Text(text) => {
+					// no space is needed at the start of a line
+					if text == " " && s.ends_with('\n') {
+						continue;
+					}
+					s.push_str(text);
+					continue;
+				},
+				Pending::Move(g, show_move_number) => (g, show_move_number)
+			};
+
+			if self.m_print_comments {
+				for c in g.get_pre_comments().iter() {
+					self.comment_to_string(c, s);
+					if !s.ends_with('\n') {
+						s.push(' ');
+					}
+				}
+			}
+
+			if show_move_number {
+				if let Some(side) = g.get_side() {
+					s.push_str(&g.get_move_number().to_string());
+					if side == &pgn_tokenizer::Side::White {
+						s.push_str(". ");
+					}
+					else {
+						s.push_str("... ");
+					}
+				}
+			}
+			
+			s.push_str(g.get_move_text());
+			self.nags_to_string(g, s);
+			
+			let mut show_num_next_move = false;
+
+			if self.m_print_comments {
+				for c in g.get_comments().iter() {
+					show_num_next_move = true;
+					s.push(' ');
+					self.comment_to_string(c, s);
+				}
+			}
+
+			// the pieces are pushed in reverse order
+			if let Some(res) = g.get_next_move() {
+				if !res.is_result() || self.prints_result() {
+					let show_variations = self.m_print_variants && !g.get_variations().is_empty();
+					if let Some(next_side) = res.get_side() {
+						show_num_next_move =
+							show_num_next_move || show_variations ||
+							next_side == &pgn_tokenizer::Side::White;
+					}
+					pending.push(Pending::Move(res, show_num_next_move));
+					pending.push(Pending::Text(" "));
+				}
+			}
+			
+			if self.m_print_variants {
+				for var in g.get_variations().iter().rev() {
+					pending.push(Pending::Text(")"));
+					pending.push(Pending::Move(var, true));
+					pending.push(Pending::Text(" ("));
 				}
-				self.to_string_rec(&res, show_num_next_move, s);
 			}
 		}
 	}
@@ -124,6 +280,132 @@ impl PgnFormatter {
 	pub fn to_string(&self, g: &game::GameTree) -> String {
 		let mut s = String::new();
 		self.to_string_rec(g, true, &mut s);
+		if self.m_export_format {
+			return self.wrap(&s);
+		}
+		s
+	}
+
+	// Formats the whole game: the tag section, with the Seven Tag Roster
+	// first, an empty line, the movetext and the termination marker.
+	pub fn format_game(&self, g: &game::Game) -> String {
+		let tree = g.get_tree();
+		let has_moves = !tree.is_result() && !tree.get_move_text().is_empty();
+
+		// last node of the main line
+		let mut last = tree;
+		while let Some(next) = last.get_next_move() {
+			last = next;
+		}
+
+		let termination =
+			if last.is_result() {
+				last.get_move_text().to_string()
+			}
+			else {
+				match g.get_result() {
+					Some(r) if is_termination_marker(r) => r.to_string(),
+					_ => "*".to_string()
+				}
+			};
+
+		let mut s = String::new();
+		for t in game::SEVEN_TAG_ROSTER.iter() {
+			let value = match (g.get_tag(t), t) {
+				(Some(value), _) => value.clone(),
+				(None, game::TagType::Date) => "????.??.??".to_string(),
+				(None, game::TagType::Result) => termination.clone(),
+				(None, _) => "?".to_string()
+			};
+			tag_to_string(t, &value, &mut s);
+		}
+		for (t, value) in g.get_tags().iter() {
+			if !game::SEVEN_TAG_ROSTER.contains(t) {
+				tag_to_string(t, value, &mut s);
+			}
+		}
+		s.push('\n');
+
+		let mut movetext = String::new();
+		if has_moves {
+			self.to_string_rec(tree, true, &mut movetext);
+			if !last.is_result() || !self.prints_result() {
+				if !movetext.ends_with('\n') {
+					movetext.push(' ');
+				}
+				movetext.push_str(&termination);
+			}
+		}
+		else if tree.is_result() {
+			// the marker of a game without moves may still have comments
+			self.to_string_rec(tree, true, &mut movetext);
+		}
+		else {
+			movetext.push_str(&termination);
+		}
+
+		if self.m_export_format {
+			s.push_str(&self.wrap(&movetext));
+		}
+		else {
+			s.push_str(&movetext);
+		}
+		if !s.ends_with('\n') {
+			s.push('\n');
+		}
 		s
 	}
+}
+
+// Splits the movetext into the words that cannot be broken across lines,
+// and tells whether each is a rest-of-line comment. Such a comment is a
+// single word, and so is a comment in braces when 'whole_comments' is
+// true. A ';' inside a comment in braces does not start a comment.
+fn movetext_words(text: &str, whole_comments: bool) -> Vec<(&str, bool)> {
+	let mut words = Vec::new();
+	let mut in_comment = false;
+	let mut i = 0;
+	while let Some(c) = text[i..].chars().next() {
+		if c.is_whitespace() {
+			i += c.len_utf8();
+			continue;
+		}
+		if c == ';' && !in_comment {
+			let end = text[i..].find('\n').map_or(text.len(), |j| i + j);
+			words.push((&text[i..end], true));
+			i = end;
+			continue;
+		}
+		if c == '{' && !in_comment && whole_comments {
+			let end = text[i..].find('}').map_or(text.len(), |j| i + j + 1);
+			words.push((&text[i..end], false));
+			i = end;
+			continue;
+		}
+
+		let end = text[i..].find(char::is_whitespace).map_or(text.len(), |j| i + j);
+		for c in text[i..end].chars() {
+			if in_comment && c == '}' {
+				in_comment = false;
+			}
+			else if !in_comment && c == '{' {
+				in_comment = true;
+			}
+		}
+		words.push((&text[i..end], false));
+		i = end;
+	}
+	words
+}
+
+fn is_termination_marker(s: &str) -> bool {
+	s == "1-0" || s == "0-1" || s == "1/2-1/2" || s == "*"
+}
+
+fn tag_to_string(t: &game::TagType, value: &str, s: &mut String) {
+	s.push('[');
+	s.push_str(&game::tag_to_string(t));
+	s.push(' ');
+	s.push_str(&pgn_tokenizer::quote_string(value));
+	s.push_str("]\n");
 }
\ No newline at end of file
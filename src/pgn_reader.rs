@@ -0,0 +1,202 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use crate::game;
+use crate::pgn_error::{PgnError, PgnErrorKind};
+use crate::pgn_tokenizer;
+use crate::pgn_tree_builder;
+
+// Reads games one at a time from any source of text, so that only the
+// text of the game being parsed is kept in memory. Spans of tokens are
+// relative to the whole input, but token indices in errors are relative
+// to the game in which the error was found.
+pub struct PgnReader<R: BufRead> {
+	m_reader: R,
+	// a line that was read but belongs to the next game
+	m_pending_line: Option<String>,
+	// offset and line of the first character not yet parsed
+	m_offset: usize,
+	m_line: usize,
+	m_games: VecDeque<Result<game::Game, PgnError>>,
+	m_finished: bool,
+	m_options: pgn_tree_builder::ParseOptions
+}
+
+// Text of a game, with the offset and line of its first character.
+struct GameText {
+	pub text: String,
+	pub offset: usize,
+	pub line: usize
+}
+
+// Whether the comment that is open at the start of the line, if any, is
+// still open at its end.
+fn ends_in_comment(line: &str, mut in_comment: bool) -> bool {
+	let mut in_quote = false;
+	let mut escaped = false;
+	for c in line.chars() {
+		if in_comment {
+			if c == '}' { in_comment = false; }
+		}
+		else if in_quote {
+			if c == '"' && !escaped { in_quote = false; }
+			escaped = c == '\\' && !escaped;
+		}
+		else if c == '{' {
+			in_comment = true;
+		}
+		else if c == ';' {
+			// the rest of the line is a comment
+			break;
+		}
+		else if c == '"' {
+			in_quote = true;
+		}
+	}
+	in_comment
+}
+
+impl<R: BufRead> PgnReader<R> {
+	pub fn new(reader: R) -> PgnReader<R> {
+		PgnReader {
+			m_reader: reader,
+			m_pending_line: None,
+			m_offset: 0,
+			m_line: 1,
+			m_games: VecDeque::new(),
+			m_finished: false,
+			m_options: pgn_tree_builder::ParseOptions::new()
+		}
+	}
+
+	pub fn set_options(&mut self, options: pgn_tree_builder::ParseOptions) {
+		self.m_options = options;
+	}
+
+	fn append_line(&mut self, text: &mut String, line: String) {
+		self.m_offset += line.len();
+		if line.ends_with('\n') {
+			self.m_line += 1;
+		}
+		text.push_str(&line);
+	}
+
+	// Reads the lines of the next game. A game ends where a line starting
+	// with '[' outside a comment follows its movetext.
+	fn read_game_text(&mut self) -> Result<Option<GameText>, PgnError> {
+		let mut text = GameText {
+			text: String::new(),
+			offset: self.m_offset,
+			line: self.m_line
+		};
+		let mut in_comment = false;
+		let mut seen_movetext = false;
+
+		if let Some(line) = self.m_pending_line.take() {
+			self.append_line(&mut text.text, line);
+		}
+
+		loop {
+			let mut line = String::new();
+			let bytes_read = self.m_reader.read_line(&mut line).map_err(|e|
+				PgnError::new(PgnErrorKind::Io { message: e.to_string() }, 0, None)
+			)?;
+			if bytes_read == 0 {
+				break;
+			}
+
+			// escape lines are neither tags nor movetext
+			if !in_comment && line.starts_with('%') {
+				self.append_line(&mut text.text, line);
+				continue;
+			}
+
+			let is_tag = !in_comment && line.trim_start().starts_with('[');
+			if is_tag && seen_movetext {
+				self.m_pending_line = Some(line);
+				break;
+			}
+			if !is_tag && !line.trim().is_empty() {
+				seen_movetext = true;
+			}
+
+			in_comment = ends_in_comment(&line, in_comment);
+			self.append_line(&mut text.text, line);
+		}
+
+		if text.text.trim().is_empty() {
+			return Ok(None);
+		}
+		Ok(Some(text))
+	}
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+	type Item = Result<game::Game, PgnError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(g) = self.m_games.pop_front() {
+				return Some(g);
+			}
+			if self.m_finished {
+				return None;
+			}
+
+			match self.read_game_text() {
+				Ok(Some(game_text)) => {
+					let (all_tokens, all_token_types, all_token_spans) =
+						pgn_tokenizer::tokenize_from(
+							game_text.text,
+							game_text.offset,
+							game_text.line
+						);
+
+					let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+					builder.set_options(self.m_options);
+					builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+					self.m_games.extend(builder.build_games());
+				},
+				Ok(None) => {
+					self.m_finished = true;
+				},
+				Err(e) => {
+					self.m_finished = true;
+					return Some(Err(e));
+				}
+			}
+		}
+	}
+}
@@ -30,8 +30,12 @@
  *
  ********************************************************************/
 
+use std::sync::OnceLock;
+
 use regex::Regex;
 
+use crate::nag;
+
 #[derive(Debug,PartialEq)]
 enum CharacterType {
 	Number,
@@ -58,13 +62,13 @@ fn classify_char(c: char, in_comment: bool) -> CharacterType {
 		'}' => CharacterType::CurlyBracket(false),
 		'[' => CharacterType::SquareBracket(true),
 		']' => CharacterType::SquareBracket(false),
-		' ' | '　' => CharacterType::Whitespace,
+		' ' | '　' | '\t' | '\n' | '\r' => CharacterType::Whitespace,
 		'*' => CharacterType::Asterisk,
 		_ =>  CharacterType::Other
 	}
 }
 
-#[derive(Debug,Eq,PartialEq,Clone)]
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
 pub enum Side { White, Black }
 
 pub fn other_side(s: &Side) -> Side {
@@ -74,96 +78,349 @@ pub fn other_side(s: &Side) -> Side {
 	}
 }
 
-#[derive(Debug,PartialEq)]
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
 pub enum ResultType { White, Draw, Black, Unknown }
 
+pub fn classify_result(s: &str) -> Option<ResultType> {
+	match s {
+		"1-0" => Some(ResultType::White),
+		"1/2-1/2" => Some(ResultType::Draw),
+		"0-1" => Some(ResultType::Black),
+		"*" => Some(ResultType::Unknown),
+		_ => None
+	}
+}
+
+pub fn result_to_string(r: &ResultType) -> String {
+	match r {
+		ResultType::White => "1-0".to_string(),
+		ResultType::Draw => "1/2-1/2".to_string(),
+		ResultType::Black => "0-1".to_string(),
+		ResultType::Unknown => "*".to_string()
+	}
+}
+
 #[derive(Debug,PartialEq)]
 pub enum TokenType {
 	VariantDelim { open: bool },
 	CommentDelim { open: bool },
+	// A comment from a ';' to the end of the line. The token is the text
+	// that follows the semicolon.
+	LineComment,
 	TagDelim { open: bool },
 	MoveNumber { id: u16, side: Side },
+	Nag { value: u8 },
 	Text,
+	// A move that only passes the turn to the other side, '--' or 'Z0'.
+	NullMove,
 	Result { result: ResultType }
 }
 
+#[derive(Debug,Eq,PartialEq,Clone,Copy,Default)]
+pub struct Span {
+	m_offset: usize,
+	m_length: usize,
+	m_line: usize,
+	m_column: usize
+}
+
+impl Span {
+	pub fn new(offset: usize, length: usize, line: usize, column: usize) -> Span {
+		Span {
+			m_offset: offset,
+			m_length: length,
+			m_line: line,
+			m_column: column
+		}
+	}
+
+	/* GETTERS */
+
+	// Offset in bytes of the first character of the token.
+	pub fn get_offset(&self) -> usize { self.m_offset }
+	// Length in bytes of the token.
+	pub fn get_length(&self) -> usize { self.m_length }
+	// Offset in bytes of the character that follows the token.
+	pub fn get_end(&self) -> usize { self.m_offset + self.m_length }
+	// Line of the first character of the token, starting at 1.
+	pub fn get_line(&self) -> usize { self.m_line }
+	// Column of the first character of the token, starting at 1.
+	pub fn get_column(&self) -> usize { self.m_column }
+}
+
 pub type AllTokens = Vec<String>;
 pub type AllTokenTypes = Vec<TokenType>;
+pub type AllTokenSpans = Vec<Span>;
 
-fn is_move_number(s: &String) -> Option<TokenType> {
-	let re = Regex::new(r"^(?<move_number>[0-9]+)(?<side>\.+)$").unwrap();
-	if let Some(capture) = re.captures(s) {
-		Some(TokenType::MoveNumber{
-			id: capture["move_number"].parse::<u16>().unwrap(),
-			side: if capture["side"] == ".".to_string() { Side::White } else { Side::Black }
-		})
-	}
-	else {
-		None
-	}
+fn is_move_number(s: &str) -> Option<TokenType> {
+	static MOVE_NUMBER_REGEX: OnceLock<Regex> = OnceLock::new();
+	let re = MOVE_NUMBER_REGEX.get_or_init(||
+		Regex::new(r"^(?<move_number>[0-9]+)(?<side>\.+)$").unwrap()
+	);
+	let capture = re.captures(s)?;
+	// a number too large to be a move number is left as text
+	let id = capture["move_number"].parse::<u16>().ok()?;
+	Some(TokenType::MoveNumber {
+		id,
+		side: if &capture["side"] == "." { Side::White } else { Side::Black }
+	})
 }
 
-fn is_result_tag(s: &String) -> Option<TokenType> {
-	if s == "*" {
-		return Some(TokenType::Result { result: ResultType::Unknown })
-	}
-	
-	if !s.contains("-") {
-		return None;
-	}
-	
-	if s == "1-0" {
-		return Some(TokenType::Result { result: ResultType::White })
-	}
-	if s == "1/2-1/2" {
-		return Some(TokenType::Result { result: ResultType::Draw })
-	}
-	if s == "0-1" {
-		return Some(TokenType::Result { result: ResultType::Black })
-	}
+pub fn is_null_move(s: &str) -> bool {
+	s == "--" || s == "Z0"
+}
 
-	return None
+fn is_result_tag(s: &str) -> Option<TokenType> {
+	classify_result(s).map(|result| TokenType::Result { result })
 }
 
-fn add_token(s: String, tokens: &mut AllTokens, token_types: &mut AllTokenTypes) {
-	if let Some(move_number) = is_move_number(&s) {
-		tokens.push(s);
-		token_types.push(move_number);
+fn add_token(
+	s: String,
+	span: Span,
+	in_comment: bool,
+	tokens: &mut AllTokens,
+	token_types: &mut AllTokenTypes,
+	token_spans: &mut AllTokenSpans
+)
+{
+	if s.is_empty() {
 		return;
 	}
-	if let Some(result) = is_result_tag(&s) {
+
+	// the text of a comment is never interpreted
+	if in_comment {
 		tokens.push(s);
-		token_types.push(result);
+		token_types.push(TokenType::Text);
+		token_spans.push(span);
 		return;
 	}
-	
-	if s != "".to_string() {
-		tokens.push(s);
+
+	// a move followed by its annotation, e.g. 'Rg8??', makes two tokens
+	if let Some((text, nag)) = nag::split_suffix_annotation(&s) {
+		let text_span = Span::new(
+			span.get_offset(),
+			text.len(),
+			span.get_line(),
+			span.get_column()
+		);
+		let nag_span = Span::new(
+			span.get_offset() + text.len(),
+			span.get_length() - text.len(),
+			span.get_line(),
+			span.get_column() + text.chars().count()
+		);
+
+		tokens.push(text.to_string());
 		token_types.push(TokenType::Text);
+		token_spans.push(text_span);
+
+		tokens.push(s[text.len()..].to_string());
+		token_types.push(TokenType::Nag { value: nag.get_value() });
+		token_spans.push(nag_span);
+		return;
+	}
+
+	let token_type =
+		if let Some(move_number) = is_move_number(&s) {
+			move_number
+		}
+		else if let Some(result) = is_result_tag(&s) {
+			result
+		}
+		else if let Some(nag) = nag::parse_nag(&s) {
+			TokenType::Nag { value: nag.get_value() }
+		}
+		else if is_null_move(&s) {
+			TokenType::NullMove
+		}
+		else {
+			TokenType::Text
+		};
+
+	tokens.push(s);
+	token_types.push(token_type);
+	token_spans.push(span);
+}
+
+// Adds the rest-of-line comment that starts at the ';' in 'start' and
+// ends right before the offset 'end'.
+fn add_line_comment(
+	mut s: String,
+	start: Span,
+	end: usize,
+	tokens: &mut AllTokens,
+	token_types: &mut AllTokenTypes,
+	token_spans: &mut AllTokenSpans
+)
+{
+	let mut length = end - start.get_offset();
+	if s.ends_with('\r') {
+		s.pop();
+		length -= 1;
 	}
+	tokens.push(s);
+	token_types.push(TokenType::LineComment);
+	token_spans.push(Span::new(start.get_offset(), length, start.get_line(), start.get_column()));
 }
 
-pub fn tokenize(s: String) -> (AllTokens, AllTokenTypes) {
+// The value of a PGN string: the text between the quotes, with the
+// escape sequences '\"' and '\\' replaced by the characters they
+// stand for.
+pub fn unquote_string(s: &str) -> String {
+	let s = s.strip_prefix('"').unwrap_or(s);
+	let s = s.strip_suffix('"').unwrap_or(s);
+
+	let mut value = String::with_capacity(s.len());
+	let mut chars = s.chars();
+	while let Some(c) = chars.next() {
+		if c == '\\' {
+			if let Some(next) = chars.next() {
+				value.push(next);
+				continue;
+			}
+		}
+		value.push(c);
+	}
+	value
+}
+
+// The PGN string whose value is 's', between quotes and with its quotes
+// and backslashes escaped.
+pub fn quote_string(s: &str) -> String {
+	let mut quoted = String::with_capacity(s.len() + 2);
+	quoted.push('"');
+	for c in s.chars() {
+		if c == '"' || c == '\\' {
+			quoted.push('\\');
+		}
+		quoted.push(c);
+	}
+	quoted.push('"');
+	quoted
+}
+
+pub fn tokenize(s: String) -> (AllTokens, AllTokenTypes, AllTokenSpans) {
+	tokenize_from(s, 0, 1)
+}
+
+// Tokenizes a piece of a larger input. 'origin' is the offset in bytes of
+// the first character of 's' and 'first_line' is its line, so that the
+// spans of the tokens are relative to the whole input.
+pub fn tokenize_from(
+	s: String,
+	origin: usize,
+	first_line: usize
+)
+-> (AllTokens, AllTokenTypes, AllTokenSpans)
+{
 	let mut tokens: AllTokens = Vec::new();
 	let mut token_types: AllTokenTypes = Vec::new();
+	let mut token_spans: AllTokenSpans = Vec::new();
 
 	let mut next_str: String = String::new();
+	// position of the first character of 'next_str'
+	let mut next_start = Span::new(origin, 0, first_line, 1);
+
+	let mut line: usize = first_line;
+	let mut column: usize = 1;
 
 	let mut in_comment = false;
 	let mut open_quote = false;
-	for c in s.chars() {
+	let mut escaped = false;
+	// position of the ';' of the rest-of-line comment being read
+	let mut line_comment: Option<Span> = None;
+	let mut in_escape_line = false;
+	for (offset, c) in s.char_indices() {
+		let offset = origin + offset;
+		let here = Span::new(offset, c.len_utf8(), line, column);
+		let pending = Span::new(
+			next_start.get_offset(),
+			offset - next_start.get_offset(),
+			next_start.get_line(),
+			next_start.get_column()
+		);
+
+		if c == '\n' {
+			line += 1;
+			column = 1;
+		}
+		else {
+			column += 1;
+		}
+
+		// a rest-of-line comment ends at the line break, which is not
+		// part of it
+		if let Some(start) = line_comment {
+			if c == '\n' {
+				add_line_comment(next_str, start, offset, &mut tokens, &mut token_types, &mut token_spans);
+				next_str = String::new();
+				line_comment = None;
+			}
+			else {
+				next_str.push(c);
+			}
+			continue;
+		}
+
+		// lines starting with '%' are escape lines and are ignored
+		if in_escape_line {
+			in_escape_line = c != '\n';
+			continue;
+		}
+		if c == '%' && here.get_column() == 1 && !in_comment && !open_quote {
+			in_escape_line = true;
+			continue;
+		}
+
+		if c == ';' && !in_comment && !open_quote {
+			add_token(next_str, pending, in_comment, &mut tokens, &mut token_types, &mut token_spans);
+			next_str = String::new();
+			line_comment = Some(here);
+			continue;
+		}
+
+		// the body of a comment is a single token with all its characters
+		if in_comment && c != '}' {
+			if next_str.is_empty() {
+				next_start = here;
+			}
+			next_str.push(c);
+			continue;
+		}
+
+		// strings keep all their characters, and a backslash escapes the
+		// character that follows it
+		if open_quote && (c != '"' || escaped) {
+			next_str.push(c);
+			escaped = c == '\\' && !escaped;
+			continue;
+		}
 		
 		match classify_char(c, in_comment) {
 			CharacterType::Number |
 			CharacterType::Letter |
 			CharacterType::Asterisk |
-			CharacterType::Other => next_str.push(c),
+			CharacterType::Other => {
+				if next_str.is_empty() {
+					next_start = here;
+				}
+				next_str.push(c);
+			},
 
 			CharacterType::Quote => {
+				if next_str.is_empty() {
+					next_start = here;
+				}
 				next_str.push(c);
 				if open_quote {
 					open_quote = false;
-					add_token(next_str, &mut tokens, &mut token_types);
+					let span = Span::new(
+						next_start.get_offset(),
+						here.get_end() - next_start.get_offset(),
+						next_start.get_line(),
+						next_start.get_column()
+					);
+					add_token(next_str, span, in_comment, &mut tokens, &mut token_types, &mut token_spans);
 					next_str = String::new();
 				}
 				else {
@@ -172,50 +429,55 @@ pub fn tokenize(s: String) -> (AllTokens, AllTokenTypes) {
 			},
 
 			CharacterType::Whitespace => {
-				if !open_quote {
-					if next_str != "".to_string() {
-						add_token(next_str, &mut tokens, &mut token_types);
-						next_str = String::new();
-					}
+				if !next_str.is_empty() {
+					add_token(next_str, pending, in_comment, &mut tokens, &mut token_types, &mut token_spans);
+					next_str = String::new();
 				}
 			},
 			CharacterType::Parenthesis(o) => {
-				if !open_quote {
-					if next_str != "".to_string() {
-						add_token(next_str, &mut tokens, &mut token_types);
-					}
-					tokens.push(c.to_string());
-					token_types.push(TokenType::VariantDelim{open: o});
-					next_str = String::new();
-				}
+				add_token(next_str, pending, in_comment, &mut tokens, &mut token_types, &mut token_spans);
+				tokens.push(c.to_string());
+				token_types.push(TokenType::VariantDelim{open: o});
+				token_spans.push(here);
+				next_str = String::new();
 			},
 			CharacterType::CurlyBracket(o) => {
-				in_comment = o;
+				add_token(next_str, pending, in_comment, &mut tokens, &mut token_types, &mut token_spans);
+				tokens.push(c.to_string());
+				token_types.push(TokenType::CommentDelim{open: o});
+				token_spans.push(here);
+				next_str = String::new();
 
-				if !open_quote {
-					if next_str != "".to_string() {
-						add_token(next_str, &mut tokens, &mut token_types);
-					}
-					tokens.push(c.to_string());
-					token_types.push(TokenType::CommentDelim{open: o});
-					next_str = String::new();
-				}
+				in_comment = o;
 			},
 			CharacterType::SquareBracket(o) => {
-				if !open_quote {
-					if next_str != "".to_string() {
-						add_token(next_str, &mut tokens, &mut token_types);
-					}
-					tokens.push(c.to_string());
-					token_types.push(TokenType::TagDelim{open: o});
-					next_str = String::new();
-				}
+				add_token(next_str, pending, in_comment, &mut tokens, &mut token_types, &mut token_spans);
+				tokens.push(c.to_string());
+				token_types.push(TokenType::TagDelim{open: o});
+				token_spans.push(here);
+				next_str = String::new();
 			}
 		}
 	}
-	add_token(next_str, &mut tokens, &mut token_types);
+	let pending = Span::new(
+		next_start.get_offset(),
+		origin + s.len() - next_start.get_offset(),
+		next_start.get_line(),
+		next_start.get_column()
+	);
+	if let Some(start) = line_comment {
+		add_line_comment(next_str, start, origin + s.len(), &mut tokens, &mut token_types, &mut token_spans);
+	}
+	else {
+		add_token(next_str, pending, in_comment, &mut tokens, &mut token_types, &mut token_spans);
+	}
 
 	assert_eq!(tokens.len(), token_types.len());
-	(tokens, token_types)
+	assert_eq!(tokens.len(), token_spans.len());
+	(tokens, token_types, token_spans)
 }
 
+pub fn tokenize_file(p: String) -> std::io::Result<(AllTokens, AllTokenTypes, AllTokenSpans)> {
+	let contents = std::fs::read_to_string(p)?;
+	Ok(tokenize(contents))
+}
@@ -31,13 +31,52 @@
  ********************************************************************/
 
 use crate::pgn_tokenizer;
+use crate::pgn_error::{PgnError, PgnErrorKind};
 use crate::game;
 use crate::comment;
+use crate::nag;
+use crate::board;
+
+// Options of the parser. In strict mode, which is the default, the first
+// error found in a game makes the game fail. Otherwise, the parser
+// recovers from errors in the movetext: wrong move numbers are ignored,
+// stray tokens are skipped and open variations are closed. The errors are
+// then kept as warnings of the game.
+#[derive(Debug,Eq,PartialEq,Clone,Copy)]
+pub struct ParseOptions {
+	m_strict: bool
+}
+
+impl Default for ParseOptions {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl ParseOptions {
+	pub fn new() -> ParseOptions {
+		ParseOptions {
+			m_strict: true
+		}
+	}
+
+	pub fn is_strict(&self) -> bool { self.m_strict }
+
+	pub fn set_strict(&mut self, v: bool) -> &mut ParseOptions {
+		self.m_strict = v;
+		self
+	}
+}
 
 pub struct PGNTreeBuilder {
 	m_tokens: pgn_tokenizer::AllTokens,
 	m_token_types: pgn_tokenizer::AllTokenTypes,
-	m_num_tokens: usize
+	m_token_spans: pgn_tokenizer::AllTokenSpans,
+	m_num_tokens: usize,
+	m_next_token: usize,
+	m_options: ParseOptions,
+	// errors recovered from in the game being built
+	m_warnings: Vec<PgnError>
 }
 
 struct ParseResult {
@@ -45,240 +84,560 @@ struct ParseResult {
 	pub next: usize
 }
 
+// A line of the game (the main line or a variation) being built.
+struct LineFrame {
+	pub nodes: Vec<game::GameTree>,
+	// move number and side of the next move of the line. The number is
+	// wider than a move number so that it may go past the last one.
+	pub move_number: u32,
+	pub side: pgn_tokenizer::Side,
+	// whether the next move must be preceded by its move number
+	pub expect_move_id: bool,
+	// whether the last move is followed by a variation or a comment
+	pub found_variant_comment: bool
+}
+
+impl LineFrame {
+	fn new(move_number: u32, side: pgn_tokenizer::Side) -> LineFrame {
+		LineFrame {
+			nodes: Vec::new(),
+			move_number,
+			side,
+			expect_move_id: true,
+			found_variant_comment: false
+		}
+	}
+
+	// Passes the turn to the other side.
+	fn advance(&mut self) {
+		self.side = pgn_tokenizer::other_side(&self.side);
+		if self.side == pgn_tokenizer::Side::White {
+			self.move_number += 1;
+		}
+	}
+
+	// Links the moves of the line, from last to first.
+	fn into_tree(self) -> Option<game::GameTree> {
+		let mut tree: Option<game::GameTree> = None;
+		for mut g in self.nodes.into_iter().rev() {
+			if let Some(next) = tree {
+				g.set_next_move(next);
+			}
+			tree = Some(g);
+		}
+		tree
+	}
+}
+
+// Ends the innermost variation where a move was expected.
+fn end_variation(lines: &mut Vec<LineFrame>) {
+	let variation = lines.pop().unwrap();
+	if let Some(variation) = variation.into_tree() {
+		lines.last_mut().unwrap().nodes.last_mut().unwrap().add_variation(variation);
+	}
+}
+
+// Ends all the open variations, from the innermost to the outermost.
+fn close_variations(lines: &mut Vec<LineFrame>) {
+	while lines.len() > 1 {
+		let variation = lines.pop().unwrap();
+		if let Some(variation) = variation.into_tree() {
+			lines.last_mut().unwrap().nodes.last_mut().unwrap().add_variation(variation);
+		}
+	}
+}
+
+impl Default for PGNTreeBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl PGNTreeBuilder {
 	pub fn new() -> PGNTreeBuilder {
 		PGNTreeBuilder {
 			m_tokens: pgn_tokenizer::AllTokens::new(),
 			m_token_types: pgn_tokenizer::AllTokenTypes::new(),
-			m_num_tokens: 0
+			m_token_spans: pgn_tokenizer::AllTokenSpans::new(),
+			m_num_tokens: 0,
+			m_next_token: 0,
+			m_options: ParseOptions::new(),
+			m_warnings: Vec::new()
 		}
 	}
+
+	pub fn set_options(&mut self, options: ParseOptions) {
+		self.m_options = options;
+	}
 	
 	pub fn set_token_list(
 		&mut self,
 		tokens: pgn_tokenizer::AllTokens,
 		token_types: pgn_tokenizer::AllTokenTypes,
+		token_spans: pgn_tokenizer::AllTokenSpans
 	)
 	{
-		assert_eq!(self.m_tokens.len(), self.m_token_types.len());
+		assert_eq!(tokens.len(), token_types.len());
+		assert_eq!(tokens.len(), token_spans.len());
 		self.m_tokens = tokens;
 		self.m_token_types = token_types;
+		self.m_token_spans = token_spans;
 		self.m_num_tokens = self.m_tokens.len();
+		self.m_next_token = 0;
 	}
 	
 	fn retrieve_token(&mut self, i: usize) -> String {
-		std::mem::replace(&mut self.m_tokens[i], String::new())
+		std::mem::take(&mut self.m_tokens[i])
 	}
-	
-	fn parse_comment_tag(&mut self, mut i: usize) -> (usize, String, String) {
-		let tag_name = self.retrieve_token(i);
-		i += 1;
-		
-		let mut text_tag = String::new();
-		let mut stop = false;
-		while i < self.m_num_tokens && !stop {
-			
-			match &self.m_token_types[i] {
-				pgn_tokenizer::TokenType::CommentDelim { open: false } => {
-					panic!("A comment tag was closed with a comment deliminter");
-				},
-				
-				pgn_tokenizer::TokenType::TagDelim { open: false } => {
-					stop = true;
-					i += 1;
-				},
-				
-				pgn_tokenizer::TokenType::Text => {
-					text_tag.push_str( &self.retrieve_token(i) );
-					i += 1;
-				},
-				
-				_ => { }
-			}
+
+	fn make_error(&self, kind: PgnErrorKind, i: usize) -> PgnError {
+		PgnError::new(kind, i, self.m_token_spans.get(i).copied())
+	}
+
+	// In strict mode the error is returned. Otherwise, it is kept as a
+	// warning and the parser carries on.
+	fn recover(&mut self, e: PgnError) -> Result<(), PgnError> {
+		if self.m_options.is_strict() {
+			return Err(e);
 		}
-		
-		(i, tag_name, text_tag)
+		self.m_warnings.push(e);
+		Ok(())
+	}
+
+	fn unexpected_token(&self, i: usize) -> PgnError {
+		if i >= self.m_num_tokens {
+			return self.make_error(PgnErrorKind::UnexpectedEndOfInput, i);
+		}
+		self.make_error(
+			PgnErrorKind::UnexpectedToken { token: self.m_tokens[i].clone() },
+			i
+		)
 	}
 	
-	fn parse_comment(&mut self, mut i: usize) -> (comment::Comment, usize) {
-		let mut com = comment::Comment::new();
-		let mut text_comment = String::new();
-		
-		let mut first_text_comment = true;
-		let mut stop = false;
-		while i < self.m_num_tokens && !stop {
-			
-			match &self.m_token_types[i] {
-				pgn_tokenizer::TokenType::CommentDelim { open: false } => {
-					stop = true;
-					i += 1;
-				},
-				
-				pgn_tokenizer::TokenType::TagDelim { open: true } => {
-					i += 1;
-					
-					let (next, tag_name, tag_text) = self.parse_comment_tag(i);
-					i = next;
-					com.add_tag(
-						comment::classify_tag(tag_name),
-						tag_text
-					);
-				},
-				
-				pgn_tokenizer::TokenType::Text => {
-					if first_text_comment {
-						first_text_comment = false;
-					}
-					else {
-						text_comment.push_str(" ");
-					}
-					text_comment.push_str( &self.retrieve_token(i) );
-					i += 1;
-				},
-				
-				_ => { }
+	fn parse_comment(&mut self, mut i: usize) -> Result<(comment::Comment, usize), PgnError> {
+		// the body of the comment is a single token
+		let body = i;
+		let mut raw_text = String::new();
+		if i < self.m_num_tokens && self.m_token_types[i] == pgn_tokenizer::TokenType::Text {
+			raw_text = self.retrieve_token(i);
+			i += 1;
+		}
+		if i == self.m_num_tokens {
+			// the comment runs to the end of the input
+			self.recover(self.make_error(PgnErrorKind::UnterminatedComment, i))?;
+		}
+		else if !matches!(&self.m_token_types[i], pgn_tokenizer::TokenType::CommentDelim { open: false }) {
+			return Err(self.unexpected_token(i));
+		}
+		else {
+			i += 1;
+		}
+
+		let com = self.comment_from_text(raw_text, body)?;
+		Ok((com, i))
+	}
+
+	// The comment with the body in the token at 'i'. A comment with an
+	// unterminated tag is all text when recovering from errors.
+	fn comment_from_text(&mut self, raw_text: String, i: usize) -> Result<comment::Comment, PgnError> {
+		match comment::parse_comment_text(raw_text.clone()) {
+			Some(com) => Ok(com),
+			None => {
+				self.recover(self.make_error(PgnErrorKind::UnterminatedCommentTag, i))?;
+				Ok(comment::comment_from_plain_text(raw_text))
 			}
 		}
-		com.set_text(text_comment);
-		(com, i)
 	}
 	
-	fn is_variant_or_comment(&self, i: usize) -> bool {
-		match &self.m_token_types[i] {
-			pgn_tokenizer::TokenType::VariantDelim { open: true } => true,
-			pgn_tokenizer::TokenType::CommentDelim { open: true } => true,
-			_ => false
+	fn is_comment_start(&self, i: usize) -> bool {
+		matches!(
+			&self.m_token_types[i],
+			pgn_tokenizer::TokenType::CommentDelim { open: true } |
+			pgn_tokenizer::TokenType::LineComment
+		)
+	}
+
+	// Parses the comment that starts at token 'i', either between curly
+	// brackets or from a ';' to the end of the line.
+	fn parse_any_comment(&mut self, i: usize) -> Result<(comment::Comment, usize), PgnError> {
+		if self.m_token_types[i] == pgn_tokenizer::TokenType::LineComment {
+			let raw_text = self.retrieve_token(i);
+			let mut comment = self.comment_from_text(raw_text, i)?;
+			comment.set_style(comment::CommentStyle::RestOfLine);
+			return Ok((comment, i + 1));
 		}
+		self.parse_comment(i + 1)
+	}
+
+	fn is_variant_comment_or_nag(&self, i: usize) -> bool {
+		matches!(
+			&self.m_token_types[i],
+			pgn_tokenizer::TokenType::VariantDelim { open: true } |
+			pgn_tokenizer::TokenType::CommentDelim { open: true } |
+			pgn_tokenizer::TokenType::LineComment |
+			pgn_tokenizer::TokenType::Nag { .. }
+		)
+	}
+
+	fn is_move(&self, i: usize) -> bool {
+		matches!(
+			&self.m_token_types[i],
+			pgn_tokenizer::TokenType::Text | pgn_tokenizer::TokenType::NullMove
+		)
+	}
+
+	fn is_variation_end(&self, i: usize) -> bool {
+		matches!(
+			self.m_token_types.get(i),
+			Some(pgn_tokenizer::TokenType::VariantDelim { open: false })
+		)
+	}
+
+	fn is_tag_section_start(&self, i: usize) -> bool {
+		matches!(
+			&self.m_token_types[i],
+			pgn_tokenizer::TokenType::TagDelim { open: true }
+		)
 	}
 	
-	fn build_game_tree_rec(
+	// Builds the main line starting at token 'i' and all its variations.
+	// Instead of recursion, the lines being built are kept in a stack: the
+	// line at the top of the stack is the innermost variation.
+	fn build_game_tree_at(
 		&mut self,
 		mut i: usize,
 		move_number: u16,
-		expect_move_id: bool,
 		side: pgn_tokenizer::Side
 	)
-	->	ParseResult
+	-> Result<ParseResult, PgnError>
 	{
-		if i == self.m_num_tokens {
-			return ParseResult { game: None, next: self.m_num_tokens };
-		}
-		
-		let mut g = game::GameTree::new();
-		if let pgn_tokenizer::TokenType::Result { result: _ } = &self.m_token_types[i] {
-			let res = self.retrieve_token(i);
-			g.set_result(res);
-			return ParseResult { game: Some(g), next: i };
-		}
+		let mut lines = vec![LineFrame::new(move_number.into(), side)];
+		let mut after_variation = false;
 
-		if let pgn_tokenizer::TokenType::MoveNumber { id, side: sid } = &self.m_token_types[i] {
-			assert_eq!(move_number, *id);
-			assert_eq!(side, *sid);
-			i += 1;
-		}
-		else if expect_move_id {
-			panic!(
-				"I was expecting a move id at move number '{move_number}', side '{:#?}'. \
-				Instead, I found token {:#?} of type '{:#?}'. \
-				Your pgn is probably malformed.",
-				side,
-				self.m_tokens[i],
-				self.m_token_types[i]
-			);
-		}
-		
-		g.set_move_text(self.retrieve_token(i), &side, move_number);
-		i += 1;
-		
-		// read a series of variants or comments
-		let mut found_variant_comment = false;
-		while i < self.m_num_tokens && self.is_variant_or_comment(i) {
-			
-			match &self.m_token_types[i] {
-				pgn_tokenizer::TokenType::VariantDelim { open: true } => {
-					found_variant_comment = true;
-
-					let parse = self.build_game_tree_rec(
-						i + 1,
-						move_number,
-						true,
-						side.clone()
-					);
-					
-					if let ParseResult { game: Some(gg), next } = parse {
-						g.add_variation(gg);
+		'next_move: loop {
+			// after a variation that ends where a move was expected, the line
+			// goes on after its last move instead of reading a new one
+			if !std::mem::take(&mut after_variation) {
+				let in_variation = lines.len() > 1;
+				let line = lines.last_mut().unwrap();
+
+				// comments before the first move of the line
+				let mut pre_comments = Vec::new();
+				if line.nodes.is_empty() {
+					while i < self.m_num_tokens && self.is_comment_start(i) {
+						let (comment, next) = self.parse_any_comment(i)?;
+						pre_comments.push(comment);
 						i = next;
 					}
-					else {
-						panic!("Unexpected wrong return");
+				}
+
+				if i == self.m_num_tokens || self.is_tag_section_start(i) {
+					if in_variation {
+						self.recover(self.make_error(PgnErrorKind::UnbalancedVariation, i))?;
+						close_variations(&mut lines);
 					}
-				},
+					// either there is nothing else to parse or the tag section
+					// of the next game starts here
+					break 'next_move;
+				}
 
-				pgn_tokenizer::TokenType::CommentDelim { open: true } => {
-					found_variant_comment = true;
+				let mut g = game::GameTree::new();
+				if let pgn_tokenizer::TokenType::Result { result: _ } = &self.m_token_types[i] {
+					if in_variation {
+						// the termination marker is skipped, and so is the
+						// variation if it ends right after it
+						self.recover(self.make_error(PgnErrorKind::UnbalancedVariation, i))?;
+						i += 1;
+						if self.is_variation_end(i) {
+							end_variation(&mut lines);
+							after_variation = true;
+							i += 1;
+						}
+						continue 'next_move;
+					}
+					let res = self.retrieve_token(i);
+					g.set_result(res);
+					for comment in pre_comments {
+						g.add_pre_comment(comment);
+					}
+					g.set_span(self.m_token_spans[i]);
+					line.nodes.push(g);
+					break 'next_move;
+				}
 
+				let Ok(move_number) = u16::try_from(line.move_number) else {
+					return Err(self.make_error(PgnErrorKind::MoveNumberOverflow, i));
+				};
+
+				if let pgn_tokenizer::TokenType::MoveNumber { id, side: sid } = self.m_token_types[i] {
+					// a wrong move number is ignored when recovering from errors
+					if move_number != id || line.side != sid {
+						self.recover(self.make_error(
+							PgnErrorKind::MoveNumberMismatch {
+								expected_id: move_number,
+								expected_side: line.side,
+								found_id: id,
+								found_side: sid
+							},
+							i
+						))?;
+					}
+					line.expect_move_id = false;
 					i += 1;
-					
-					let (comment, next) = self.parse_comment(i);
-					g.add_comment(comment);
-					i = next;
+				}
+				else if line.expect_move_id && self.is_move(i) {
+					self.recover(self.unexpected_token(i))?;
 				}
 
-				_ => {}
-			}
-		}
-		
-		if i < self.m_num_tokens {
-			
-			if let pgn_tokenizer::TokenType::VariantDelim { open: false } = &self.m_token_types[i] {
-				return ParseResult { game: Some(g), next: i + 1 };
+				if i == self.m_num_tokens || !self.is_move(i) {
+					self.recover(self.unexpected_token(i))?;
+
+					// the variation ends where a move was expected
+					if in_variation && self.is_variation_end(i) {
+						end_variation(&mut lines);
+						after_variation = true;
+					}
+
+					// the token is skipped
+					if i < self.m_num_tokens {
+						i += 1;
+					}
+					continue 'next_move;
+				}
+				g.set_move_text(self.retrieve_token(i), &line.side, move_number);
+				for comment in pre_comments {
+					g.add_pre_comment(comment);
+				}
+				g.set_span(self.m_token_spans[i]);
+				line.nodes.push(g);
+				line.found_variant_comment = false;
+				i += 1;
 			}
-			
-			let next_side = pgn_tokenizer::other_side(&side);
-			let next_move = move_number + if next_side == pgn_tokenizer::Side::White { 1 } else { 0 };
-			let parse = self.build_game_tree_rec(
-				i,
-				next_move,
-				found_variant_comment,
-				next_side
-			);
-			
-			if let ParseResult { game: Some(gg), next } = parse {
-				g.set_next_move(gg);
-				i = next;
+
+			loop {
+				let in_variation = lines.len() > 1;
+				let line = lines.last_mut().unwrap();
+
+				// read a series of variants, comments or annotation glyphs
+				while i < self.m_num_tokens && self.is_variant_comment_or_nag(i) {
+
+					match &self.m_token_types[i] {
+						pgn_tokenizer::TokenType::Nag { value } => {
+							line.nodes.last_mut().unwrap().add_nag(nag::Nag::new(*value));
+							i += 1;
+						},
+
+						pgn_tokenizer::TokenType::VariantDelim { open: true } => {
+							// an empty variation is skipped
+							if self.is_variation_end(i + 1) {
+								self.recover(self.unexpected_token(i + 1))?;
+								i += 2;
+								continue;
+							}
+
+							line.found_variant_comment = true;
+
+							// the variation is an alternative to the last move
+							let variation = LineFrame::new(line.move_number, line.side);
+							lines.push(variation);
+							i += 1;
+							continue 'next_move;
+						},
+
+						pgn_tokenizer::TokenType::CommentDelim { open: true } |
+						pgn_tokenizer::TokenType::LineComment => {
+							line.found_variant_comment = true;
+
+							let (comment, next) = self.parse_any_comment(i)?;
+							line.nodes.last_mut().unwrap().add_comment(comment);
+							i = next;
+						}
+
+						_ => {}
+					}
+				}
+
+				if i == self.m_num_tokens {
+					if in_variation {
+						self.recover(self.make_error(PgnErrorKind::UnbalancedVariation, i))?;
+						close_variations(&mut lines);
+					}
+					break 'next_move;
+				}
+
+				if let pgn_tokenizer::TokenType::VariantDelim { open: false } = &self.m_token_types[i] {
+					if !in_variation {
+						// the parenthesis is skipped
+						self.recover(self.make_error(PgnErrorKind::UnbalancedVariation, i))?;
+						i += 1;
+						continue;
+					}
+					i += 1;
+
+					// the variation ends here
+					let variation = lines.pop().unwrap().into_tree().unwrap();
+					let line = lines.last_mut().unwrap();
+					line.nodes.last_mut().unwrap().add_variation(variation);
+					continue;
+				}
+
+				line.advance();
+				line.expect_move_id = line.found_variant_comment;
+				continue 'next_move;
 			}
 		}
-		
-		ParseResult { game: Some(g), next: i }
+
+		Ok(ParseResult { game: lines.pop().unwrap().into_tree(), next: i })
 	}
-	
-	pub fn build_game_tree(&mut self, i: usize) -> Option<game::GameTree> {
 
-		let parse_result = self.build_game_tree_rec(
-			i,
-			1,
-			true,
-			pgn_tokenizer::Side::White
-		);
-		
-		parse_result.game
+	pub fn build_game_tree(&mut self, i: usize) -> Result<Option<game::GameTree>, PgnError> {
+		Ok(self.build_game_tree_at(i, 1, pgn_tokenizer::Side::White)?.game)
 	}
 
-	pub fn build_game(&mut self) -> Option<game::Game> {
-		let mut g = game::Game::new();
+	// Index of the first token of the game that follows the token at 'i'.
+	// A tag section starts at a '[' outside a comment that does not
+	// follow a ']'.
+	fn find_next_game(&self, start: usize, i: usize) -> usize {
+		let mut in_comment = false;
+		for j in start..self.m_num_tokens {
+			match &self.m_token_types[j] {
+				pgn_tokenizer::TokenType::CommentDelim { open } => {
+					in_comment = *open;
+				},
+				pgn_tokenizer::TokenType::TagDelim { open: true }
+					if
+						j > i && !in_comment &&
+						!matches!(
+							&self.m_token_types[j - 1],
+							pgn_tokenizer::TokenType::TagDelim { open: false }
+						)
+				=> {
+					return j;
+				},
+				_ => { }
+			}
+		}
+		self.m_num_tokens
+	}
+
+	fn parse_tag_section(&mut self, g: &mut game::Game, mut i: usize) -> Result<usize, PgnError> {
+		// index and value of the 'FEN' tag, and value of the 'SetUp' tag
+		let mut fen: Option<(usize, String)> = None;
+		let mut set_up: Option<String> = None;
+
+		while i < self.m_num_tokens && self.is_tag_section_start(i) {
+			if i + 3 >= self.m_num_tokens {
+				return Err(self.make_error(PgnErrorKind::UnexpectedEndOfInput, self.m_num_tokens));
+			}
+			if !matches!(&self.m_token_types[i + 3], pgn_tokenizer::TokenType::TagDelim { open: false }) {
+				return Err(self.unexpected_token(i + 3));
+			}
 
-		let mut i = 0;
-		while let pgn_tokenizer::TokenType::TagDelim { open: true } = &self.m_token_types[i] {
 			let tag_type = game::classify(self.retrieve_token(i + 1));
-			g.add_game_tag((tag_type, self.retrieve_token(i + 2)));
+			let value = pgn_tokenizer::unquote_string(&self.retrieve_token(i + 2));
+			match tag_type {
+				game::TagType::FEN => fen = Some((i + 2, value.clone())),
+				game::TagType::SetUp => set_up = Some(value.clone()),
+				_ => { }
+			}
+			g.add_game_tag((tag_type, value));
 
 			i += 4;
 		}
 
-		let parse_result = self.build_game_tree(i);
+		// a 'SetUp' tag other than "1" means that the 'FEN' tag is ignored
+		let honour_fen = match &set_up {
+			Some(value) => value == "1",
+			None => true
+		};
+		if let (Some((j, fen)), true) = (fen, honour_fen) {
+			match board::parse_fen(&fen) {
+				Some(p) => g.set_initial_position(p),
+				None => return Err(self.make_error(PgnErrorKind::InvalidFen { fen }, j))
+			}
+		}
+		Ok(i)
+	}
+
+	// Builds the game that starts at token 'i'. None when there is neither
+	// a tag section nor a move before the next game, as with the comments
+	// that may come between games.
+	fn build_game_at(&mut self, mut i: usize) -> Result<(Option<game::Game>, usize), PgnError> {
+		let mut g = game::Game::new();
+		self.m_warnings.clear();
+
+		i = self.parse_tag_section(&mut g, i)?;
+
+		let parse_result = self.build_game_tree_at(
+			i,
+			g.get_initial_position().get_fullmove_number(),
+			g.get_initial_position().get_side_to_move()
+		)?;
+		i = parse_result.next;
+		match parse_result.game {
+			Some(tree) => g.set_tree(tree),
+			None if g.get_tags().is_empty() => return Ok((None, i)),
+			None => ()
+		}
+		for warning in std::mem::take(&mut self.m_warnings) {
+			g.add_warning(warning);
+		}
+
+		if
+			i < self.m_num_tokens &&
+			matches!(&self.m_token_types[i], pgn_tokenizer::TokenType::Result { .. })
+		{
+			i += 1;
 
-		g.set_tree(parse_result.unwrap());
+			// the comments after the termination marker belong to it
+			let mut last = g.get_tree_mut();
+			while last.get_next_move().is_some() {
+				last = last.get_next_move_mut().as_deref_mut().unwrap();
+			}
+			while i < self.m_num_tokens && self.is_comment_start(i) {
+				let (comment, next) = self.parse_any_comment(i)?;
+				last.add_comment(comment);
+				i = next;
+			}
+		}
+		Ok((Some(g), i))
+	}
 
-		Some(g)
+	// Builds the game that follows the last game built, if any. When a game
+	// is malformed, the error is returned and the next call to this function
+	// continues with the next game.
+	pub fn build_game(&mut self) -> Result<Option<game::Game>, PgnError> {
+		loop {
+			let start = self.m_next_token;
+			if start >= self.m_num_tokens {
+				return Ok(None);
+			}
+
+			match self.build_game_at(start) {
+				Ok((Some(g), next)) => {
+					self.m_next_token = next;
+					return Ok(Some(g));
+				},
+				// there is nothing to build before the next game
+				Ok((None, next)) => self.m_next_token = next,
+				Err(e) => {
+					self.m_next_token = self.find_next_game(start, e.get_token_index());
+					return Err(e);
+				}
+			}
+		}
+	}
+
+	// Builds all the remaining games in the list of tokens.
+	pub fn build_games(&mut self) -> Vec<Result<game::Game, PgnError>> {
+		let mut games = Vec::new();
+		loop {
+			match self.build_game() {
+				Ok(Some(g)) => games.push(Ok(g)),
+				Ok(None) => break,
+				Err(e) => games.push(Err(e))
+			}
+		}
+		games
 	}
 }
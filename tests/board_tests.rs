@@ -0,0 +1,422 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use pgn_parser::board;
+	use pgn_parser::board::{MoveErrorKind, PieceType, Position};
+	use pgn_parser::game;
+	use pgn_parser::pgn_tree_builder;
+	use pgn_parser::pgn_tokenizer;
+	use pgn_parser::pgn_tokenizer::Side;
+
+	fn make_game(file: String) -> game::Game {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.build_game().unwrap().unwrap()
+	}
+
+	fn perft(p: &Position, depth: usize) -> usize {
+		if depth == 0 {
+			return 1;
+		}
+		p.legal_moves()
+			.iter()
+			.map(
+				|m| {
+					let mut next = p.clone();
+					next.play(m);
+					perft(&next, depth - 1)
+				}
+			)
+			.sum()
+	}
+
+	fn play_all(sans: &[&str]) -> Position {
+		let mut p = Position::new();
+		for san in sans.iter() {
+			let m = p.parse_san(san).unwrap();
+			p.play(&m);
+		}
+		p
+	}
+
+	fn square(s: &str) -> board::Square {
+		board::parse_square(s).unwrap()
+	}
+
+	#[test]
+	fn perft_start_position() {
+		let p = Position::new();
+		assert_eq!(perft(&p, 1), 20);
+		assert_eq!(perft(&p, 2), 400);
+		assert_eq!(perft(&p, 3), 8902);
+	}
+
+	#[test]
+	fn squares() {
+		assert_eq!(square("a1"), board::Square::new(0, 0));
+		assert_eq!(square("h8"), board::Square::new(7, 7));
+		assert_eq!(square("e4").to_string(), "e4");
+		assert_eq!(board::parse_square("i1"), None);
+		assert_eq!(board::parse_square("a9"), None);
+		assert_eq!(board::parse_square("a10"), None);
+	}
+
+	#[test]
+	fn san_basic() {
+		let p = Position::new();
+		assert_eq!(p.parse_san("e4"), Ok(board::Move::new(square("e2"), square("e4"), None)));
+		assert_eq!(p.parse_san("Nf3"), Ok(board::Move::new(square("g1"), square("f3"), None)));
+		assert_eq!(p.parse_san("Nf3+!?"), Ok(board::Move::new(square("g1"), square("f3"), None)));
+		assert_eq!(p.parse_san("e5"), Err(MoveErrorKind::IllegalMove));
+		assert_eq!(p.parse_san("Ke2"), Err(MoveErrorKind::IllegalMove));
+		assert_eq!(p.parse_san("Cc3"), Err(MoveErrorKind::InvalidSan));
+		assert_eq!(p.parse_san("O-O"), Err(MoveErrorKind::IllegalMove));
+	}
+
+	#[test]
+	fn san_castling() {
+		let p = play_all(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O"]);
+		assert_eq!(p.get_piece(square("g1")), Some(board::Piece::new(PieceType::King, Side::White)));
+		assert_eq!(p.get_piece(square("f1")), Some(board::Piece::new(PieceType::Rook, Side::White)));
+		assert!(!p.can_castle(Side::White, true));
+		assert!(!p.can_castle(Side::White, false));
+		assert!(p.can_castle(Side::Black, true));
+
+		let p = play_all(&["d4", "d5", "Nc3", "Nc6", "Bf4", "Bf5", "Qd2", "Qd7", "O-O-O", "O-O-O"]);
+		assert_eq!(p.get_piece(square("c1")), Some(board::Piece::new(PieceType::King, Side::White)));
+		assert_eq!(p.get_piece(square("d8")), Some(board::Piece::new(PieceType::Rook, Side::Black)));
+	}
+
+	#[test]
+	fn san_castling_through_check() {
+		// the bishop on a6 attacks f1
+		let p = play_all(&["e4", "b6", "Nf3", "Ba6", "Bc4", "e6", "Bb3", "Nf6"]);
+		assert_eq!(p.parse_san("O-O"), Err(MoveErrorKind::IllegalMove));
+
+		let p = play_all(&["e4", "b6", "Nf3", "Ba6", "Bc4", "e6", "Bb3", "Nf6", "d3", "Be7"]);
+		assert_eq!(p.parse_san("O-O"), Ok(board::Move::new(square("e1"), square("g1"), None)));
+
+		// castling out of check
+		let p = play_all(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "d4", "Bb4+"]);
+		assert_eq!(p.parse_san("O-O"), Err(MoveErrorKind::IllegalMove));
+	}
+
+	#[test]
+	fn san_en_passant() {
+		let p = play_all(&["e4", "a6", "e5", "d5"]);
+		assert_eq!(p.get_en_passant(), Some(square("d6")));
+
+		let m = p.parse_san("exd6").unwrap();
+		let mut p = p.clone();
+		p.play(&m);
+		assert_eq!(p.get_piece(square("d5")), None);
+		assert_eq!(p.get_piece(square("d6")), Some(board::Piece::new(PieceType::Pawn, Side::White)));
+		assert_eq!(p.get_en_passant(), None);
+	}
+
+	#[test]
+	fn san_promotion() {
+		let p = play_all(&["h4", "g5", "hxg5", "h6", "gxh6", "Nf6", "h7", "Ng8"]);
+		assert_eq!(
+			p.parse_san("hxg8=Q"),
+			Ok(board::Move::new(square("h7"), square("g8"), Some(PieceType::Queen)))
+		);
+		assert_eq!(
+			p.parse_san("hxg8N"),
+			Ok(board::Move::new(square("h7"), square("g8"), Some(PieceType::Knight)))
+		);
+		assert_eq!(p.parse_san("hxg8"), Err(MoveErrorKind::IllegalMove));
+		// the rook on h8 blocks the pawn
+		assert_eq!(p.parse_san("h8=Q"), Err(MoveErrorKind::IllegalMove));
+	}
+
+	#[test]
+	fn san_ambiguity() {
+		let p = play_all(&["d4", "d5", "Nf3", "Nf6"]);
+		assert_eq!(p.parse_san("Nd2"), Err(MoveErrorKind::AmbiguousMove));
+		assert_eq!(p.parse_san("Nbd2"), Ok(board::Move::new(square("b1"), square("d2"), None)));
+		assert_eq!(p.parse_san("Nfd2"), Ok(board::Move::new(square("f3"), square("d2"), None)));
+		assert_eq!(p.parse_san("N3d2"), Ok(board::Move::new(square("f3"), square("d2"), None)));
+		assert_eq!(p.parse_san("Nb1d2"), Ok(board::Move::new(square("b1"), square("d2"), None)));
+		assert_eq!(p.parse_san("Ng1d2"), Err(MoveErrorKind::IllegalMove));
+	}
+
+	#[test]
+	fn san_pinned_piece() {
+		let p = play_all(&["e4", "e5", "d4", "Bb4+"]);
+		assert!(p.is_in_check());
+		assert_eq!(p.parse_san("Nf3"), Err(MoveErrorKind::IllegalMove));
+		assert_eq!(p.parse_san("c3"), Ok(board::Move::new(square("c2"), square("c3"), None)));
+
+		let p = play_all(&["e4", "e5", "d3", "Bb4+", "Nc3", "a6"]);
+		// the knight on c3 is pinned
+		assert_eq!(p.parse_san("Nd5"), Err(MoveErrorKind::IllegalMove));
+	}
+
+	#[test]
+	fn replay_full_games() {
+		for file in ["full_game_ii", "full_game_xi", "full_game_xx"] {
+			let mut g = make_game(format!("sample_games/{file}.pgn"));
+			assert_eq!(g.replay_moves(), vec![]);
+
+			let tree = g.get_tree();
+			assert_eq!(
+				tree.get_resolved_move(),
+				&Some(board::Move::new(square("d2"), square("d4"), None))
+			);
+		}
+	}
+
+	#[test]
+	fn replay_variations() {
+		let mut g = make_game("sample_games/0004-r.pgn".to_string());
+		assert_eq!(g.replay_moves(), vec![]);
+
+		let tree = g.get_tree();
+		assert_eq!(
+			tree.get_variations()[1].get_resolved_move(),
+			&Some(board::Move::new(square("f2"), square("f4"), None))
+		);
+	}
+
+	#[test]
+	fn replay_errors() {
+		let mut g = make_game("sample_games/0015.pgn".to_string());
+		let errors = g.replay_moves();
+		assert_eq!(errors.len(), 1);
+		assert_eq!(errors[0].get_kind(), &MoveErrorKind::InvalidSan);
+		assert_eq!(errors[0].get_move_number(), 1);
+		assert_eq!(errors[0].get_side(), &Side::Black);
+		assert_eq!(errors[0].get_move_text(), "Cc6");
+		assert_eq!(errors[0].get_span(), &Some(pgn_tokenizer::Span::new(146, 3, 1, 147)));
+
+		// the main line is replayed even if a variation is wrong
+		let main = g.get_tree().get_next_move().as_ref().unwrap();
+		assert_eq!(
+			main.get_resolved_move(),
+			&Some(board::Move::new(square("d7"), square("d5"), None))
+		);
+		let var = g.get_tree().get_variations()[1].get_next_move().as_ref().unwrap();
+		assert_eq!(var.get_resolved_move(), &None);
+	}
+
+	#[test]
+	fn perft_fen_positions() {
+		let positions = [
+			("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3, 97862),
+			("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43238),
+			("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3, 9467),
+			("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 3, 62379),
+		];
+		for (fen, depth, nodes) in positions.iter() {
+			let p = board::parse_fen(fen).unwrap();
+			assert_eq!(perft(&p, *depth), *nodes);
+		}
+	}
+
+	#[test]
+	fn fen_round_trip() {
+		assert_eq!(
+			Position::new().to_fen(),
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+		);
+		assert_eq!(
+			board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+			Some(Position::new())
+		);
+
+		let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+		assert_eq!(board::parse_fen(fen).unwrap().to_fen(), fen);
+
+		// the clocks are optional
+		assert_eq!(
+			board::parse_fen("8/8/8/8/8/8/8/K6k b - -").unwrap().to_fen(),
+			"8/8/8/8/8/8/8/K6k b - - 0 1"
+		);
+
+		assert_eq!(board::parse_fen("8/8/8/8/8/8/8 w - - 0 1"), None);
+		assert_eq!(board::parse_fen("8/8/8/8/8/8/8/K7k w - - 0 1"), None);
+		assert_eq!(board::parse_fen("8/8/8/8/8/8/8/K6x w - - 0 1"), None);
+		assert_eq!(board::parse_fen("8/8/8/8/8/8/8/K6k x - - 0 1"), None);
+		assert_eq!(board::parse_fen("8/8/8/8/8/8/8/K6k w X - 0 1"), None);
+		assert_eq!(board::parse_fen("8/8/8/8/8/8/8/K6k w - e9 0 1"), None);
+		assert_eq!(board::parse_fen("99999999999999999999999999999999/8/8/8/8/8/8/8 w - - 0 1"), None);
+		assert_eq!(board::parse_fen("08/8/8/8/8/8/8/K6k w - - 0 1"), None);
+	}
+
+	#[test]
+	fn fen_of_nodes() {
+		let mut g = make_game("sample_games/0003-r.pgn".to_string());
+		assert_eq!(g.replay_moves(), vec![]);
+
+		let tree = g.get_tree();
+		assert_eq!(
+			tree.get_fen(),
+			Some("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1".to_string())
+		);
+		assert_eq!(
+			tree.get_next_move().as_ref().unwrap().get_fen(),
+			Some("rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq d6 0 2".to_string())
+		);
+
+		let var = tree.get_variations()[0].get_next_move().as_ref().unwrap();
+		assert_eq!(
+			var.get_fen(),
+			Some("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2".to_string())
+		);
+
+		// results have no position
+		let result = tree
+			.get_next_move().as_ref().unwrap()
+			.get_next_move().as_ref().unwrap();
+		assert!(result.is_result());
+		assert_eq!(result.get_fen(), None);
+	}
+
+	#[test]
+	fn fen_clocks() {
+		let p = play_all(&["Nf3", "Nf6", "Ng1", "Ng8", "e4"]);
+		assert_eq!(p.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3");
+
+		let p = play_all(&["Nf3", "Nf6", "Ng1", "Ng8"]);
+		assert_eq!(p.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3");
+
+		let p = play_all(&["e4", "d5", "exd5", "Qxd5", "Ke2"]);
+		assert_eq!(p.to_fen(), "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPPKPPP/RNBQ1BNR b kq - 1 3");
+	}
+
+	#[test]
+	fn fen_tag_black_to_move() {
+		let mut g = make_game("sample_games/fen_0000.pgn".to_string());
+		assert_eq!(
+			g.get_initial_position().to_fen(),
+			"6k1/5ppp/8/8/7q/8/5PP1/6K1 b - - 0 23"
+		);
+
+		let tree = g.get_tree();
+		assert_eq!(tree.get_move_text(), "Qh2+");
+		assert_eq!(tree.get_move_number(), &23);
+		assert_eq!(tree.get_side(), &Some(Side::Black));
+
+		assert_eq!(g.replay_moves(), vec![]);
+		let mut node = g.get_tree();
+		while let Some(next) = node.get_next_move().as_ref() {
+			if next.is_result() {
+				break;
+			}
+			node = next;
+		}
+		assert_eq!(node.get_fen(), Some("6k1/5ppp/8/8/8/8/4KPq1/8 w - - 0 26".to_string()));
+	}
+
+	#[test]
+	fn fen_tag_white_to_move() {
+		let mut g = make_game("sample_games/fen_0001.pgn".to_string());
+		assert_eq!(g.get_tree().get_move_number(), &40);
+		assert_eq!(g.get_tree().get_side(), &Some(Side::White));
+		assert_eq!(g.replay_moves(), vec![]);
+
+		let var = &g.get_tree()
+			.get_next_move().as_ref().unwrap()
+			.get_next_move().as_ref().unwrap()
+			.get_variations()[0];
+		assert_eq!(var.get_fen(), Some("8/3k4/8/8/4P3/8/3K4/8 b - - 2 41".to_string()));
+	}
+
+	#[test]
+	fn fen_tag_ignored() {
+		let mut g = make_game("sample_games/fen_0002.pgn".to_string());
+		assert_eq!(g.get_initial_position(), &Position::new());
+		assert_eq!(g.get_tree().get_move_number(), &1);
+		assert_eq!(g.replay_moves(), vec![]);
+	}
+
+	#[test]
+	fn replay_null_moves() {
+		let mut g = make_game("sample_games/nullmove_0000.pgn".to_string());
+		assert_eq!(g.replay_moves(), vec![]);
+
+		let path = |s: &str| pgn_parser::game_cursor::parse_node_path(s).unwrap();
+		let tree = g.get_tree();
+
+		let null = tree.get_node(&path("4/0.0")).unwrap();
+		assert!(null.is_null_move());
+		assert_eq!(null.get_move_text(), "--");
+		assert_eq!(null.get_resolved_move(), &None);
+		assert_eq!(
+			null.get_fen(),
+			Some("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 3 3".to_string())
+		);
+		assert_eq!(tree.get_node(&path("4/0.1")).unwrap().get_move_text(), "d5");
+
+		let null = tree.get_node(&path("6/0.0")).unwrap();
+		assert!(null.is_null_move());
+		assert_eq!(null.get_move_text(), "Z0");
+		assert_eq!(
+			null.get_fen(),
+			Some("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 5 4".to_string())
+		);
+
+		assert!(!tree.get_node(&path("6")).unwrap().is_null_move());
+	}
+
+	#[test]
+	fn null_move_clears_en_passant() {
+		let mut p = play_all(&["e4"]);
+		assert_eq!(p.get_en_passant(), Some(square("e3")));
+		p.play_null_move();
+		assert_eq!(p.get_en_passant(), None);
+		assert_eq!(p.get_side_to_move(), Side::White);
+		assert_eq!(p.get_fullmove_number(), 2);
+	}
+
+	#[test]
+	fn saturated_clocks() {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize("[FEN \"8/8/8/8/8/8/8/K6k w - - 65535 10\"] 10. Ka2 *".to_string());
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let mut g = builder.build_game().unwrap().unwrap();
+		assert_eq!(g.replay_moves(), vec![]);
+		assert_eq!(g.get_tree().get_fen(), Some("8/8/8/8/8/8/K7/7k b - - 65535 10".to_string()));
+
+		let mut p = board::parse_fen("8/8/8/8/8/8/8/K6k b - - 65535 65535").unwrap();
+		p.play_null_move();
+		assert_eq!(p.to_fen(), "8/8/8/8/8/8/8/K6k w - - 65535 65535");
+	}
+}
@@ -0,0 +1,277 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use std::time::Duration;
+
+	use pgn_parser::board;
+	use pgn_parser::comment;
+	use pgn_parser::game;
+	use pgn_parser::pgn_formatter;
+	use pgn_parser::pgn_tree_builder;
+	use pgn_parser::pgn_tokenizer;
+
+	fn make_game(file: String) -> game::GameTree {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.build_game_tree(0).unwrap().unwrap()
+	}
+
+	#[test]
+	fn durations() {
+		assert_eq!(comment::parse_duration("1:02:03"), Some(Duration::from_secs(3723)));
+		assert_eq!(comment::parse_duration("0:02:59.9"), Some(Duration::from_millis(179900)));
+		assert_eq!(comment::parse_duration("10:00"), Some(Duration::from_secs(600)));
+		assert_eq!(comment::parse_duration("19"), Some(Duration::from_secs(19)));
+		assert_eq!(comment::parse_duration("0:00:00.125"), Some(Duration::from_millis(125)));
+		assert_eq!(comment::parse_duration("120:00:00"), Some(Duration::from_secs(432000)));
+
+		assert_eq!(comment::parse_duration(""), None);
+		assert_eq!(comment::parse_duration("1:60:00"), None);
+		assert_eq!(comment::parse_duration("1:00:60"), None);
+		assert_eq!(comment::parse_duration("1:2:3:4"), None);
+		assert_eq!(comment::parse_duration("1::03"), None);
+		assert_eq!(comment::parse_duration("0:01."), None);
+		assert_eq!(comment::parse_duration("-0:01"), None);
+		assert_eq!(comment::parse_duration("a:01"), None);
+		assert_eq!(comment::parse_duration("1000000000000000000:00"), None);
+		assert_eq!(comment::parse_duration("10000000000000000:00:00"), None);
+	}
+
+	#[test]
+	fn durations_to_string() {
+		assert_eq!(comment::duration_to_string(&Duration::from_secs(3723)), "1:02:03");
+		assert_eq!(comment::duration_to_string(&Duration::from_secs(600)), "0:10:00");
+		assert_eq!(comment::duration_to_string(&Duration::from_millis(179900)), "0:02:59.9");
+		assert_eq!(comment::duration_to_string(&Duration::from_millis(125)), "0:00:00.125");
+
+		for s in ["1:02:03", "0:02:59.9", "25:00:00", "0:00:00"] {
+			let d = comment::parse_duration(s).unwrap();
+			assert_eq!(comment::duration_to_string(&d), s);
+		}
+	}
+
+	#[test]
+	fn clock_0000() {
+		let g = make_game("sample_games/clock_0000.pgn".to_string());
+		assert_eq!(g.get_clock(), Some(Duration::from_secs(3723)));
+		assert_eq!(g.get_elapsed_move_time(), None);
+
+		let g = g.get_next_move().as_ref().unwrap();
+		assert_eq!(g.get_clock(), Some(Duration::from_millis(179900)));
+		assert_eq!(g.get_elapsed_move_time(), Some(Duration::from_millis(1500)));
+		assert_eq!(
+			g.get_comments()[0].get_tag(&comment::TagType::ElapsedMoveTime),
+			Some(&"0:00:01.5".to_string())
+		);
+
+		let g = g.get_next_move().as_ref().unwrap();
+		assert_eq!(g.get_clock(), Some(Duration::from_secs(600)));
+		// the value is kept as it was written
+		assert_eq!(g.get_comments()[0].get_tag(&comment::TagType::Clock), Some(&"10:00".to_string()));
+		assert_eq!(g.get_comments()[0].get_text(), "Fast");
+	}
+
+	#[test]
+	fn set_clock() {
+		let mut c = comment::Comment::new();
+		assert_eq!(c.get_clock(), None);
+
+		c.set_clock(&Duration::from_secs(90));
+		c.set_elapsed_move_time(&Duration::from_millis(2500));
+		c.set_clock(&Duration::from_secs(3600));
+		assert_eq!(
+			c.get_tags(),
+			&vec![
+				(comment::TagType::Clock, "1:00:00".to_string()),
+				(comment::TagType::ElapsedMoveTime, "0:00:02.5".to_string())
+			]
+		);
+		assert_eq!(c.get_clock(), Some(Duration::from_secs(3600)));
+		assert_eq!(comment::tag_to_string(&comment::TagType::ElapsedMoveTime), "%emt");
+	}
+
+	#[test]
+	fn evaluations() {
+		let eval = |s: &str| comment::parse_evaluation(s);
+		let cp = |cp: i32, depth: Option<u16>| Some(comment::Evaluation::new(comment::Score::Centipawns(cp), depth));
+		let mate = |n: i32, depth: Option<u16>| Some(comment::Evaluation::new(comment::Score::MateIn(n), depth));
+
+		assert_eq!(eval("0.17"), cp(17, None));
+		assert_eq!(eval("-1.5"), cp(-150, None));
+		assert_eq!(eval("+0.3"), cp(30, None));
+		assert_eq!(eval("2"), cp(200, None));
+		assert_eq!(eval("-50"), cp(-5000, None));
+		assert_eq!(eval("0.0"), cp(0, None));
+		assert_eq!(eval("#3"), mate(3, None));
+		assert_eq!(eval("#-2"), mate(-2, None));
+		assert_eq!(eval("0.17,20"), cp(17, Some(20)));
+		assert_eq!(eval("#-2,35"), mate(-2, Some(35)));
+
+		assert_eq!(eval(""), None);
+		assert_eq!(eval("#"), None);
+		assert_eq!(eval("#1.5"), None);
+		assert_eq!(eval("1.2.3"), None);
+		assert_eq!(eval("inf"), None);
+		assert_eq!(eval("0.17,"), None);
+		assert_eq!(eval("0.17,-3"), None);
+		assert_eq!(eval("0.17,20,1"), None);
+
+		let e = eval("-1.5,18").unwrap();
+		assert_eq!(e.get_centipawns(), Some(-150));
+		assert_eq!(e.get_mate_in(), None);
+		assert_eq!(e.get_depth(), Some(18));
+		let e = eval("#4").unwrap();
+		assert_eq!(e.get_centipawns(), None);
+		assert_eq!(e.get_mate_in(), Some(4));
+	}
+
+	#[test]
+	fn evaluations_to_string() {
+		for s in ["0.17", "-1.5", "0.0", "1.0", "0.05", "-0.01", "#3", "#-2", "0.17,20", "#-2,35"] {
+			let e = comment::parse_evaluation(s).unwrap();
+			assert_eq!(comment::evaluation_to_string(&e), s);
+		}
+
+		let mut c = comment::Comment::new();
+		c.set_evaluation(&comment::Evaluation::new(comment::Score::Centipawns(-230), Some(12)));
+		assert_eq!(c.get_tag(&comment::TagType::Eval), Some(&"-2.3,12".to_string()));
+	}
+
+	#[test]
+	fn evaluations_of_moves() {
+		let g = make_game("sample_games/full_game_ii.pgn".to_string());
+		assert_eq!(g.get_evaluation().unwrap().get_centipawns(), Some(14));
+
+		let mut node = &g;
+		let mut evaluations = Vec::new();
+		while let Some(e) = node.get_evaluation() {
+			evaluations.push(e.get_centipawns().unwrap());
+			node = node.get_next_move().as_ref().unwrap();
+		}
+		assert_eq!(evaluations[..6], [14, 24, 20, 14, 0, 35]);
+
+		// the value is written back as it was read
+		let s = pgn_formatter::PgnFormatter::new().to_string(&g);
+		assert!(s.starts_with("1. d4 { [%eval 0.14] } 1... d5 { [%eval 0.24] } 2. Nf3 { [%eval 0.2] }"));
+	}
+
+	fn square(s: &str) -> board::Square {
+		board::parse_square(s).unwrap()
+	}
+
+	#[test]
+	fn arrows_and_highlights() {
+		use comment::MarkupColor::*;
+
+		assert_eq!(
+			comment::parse_arrows("Gd2d4,Re7e5"),
+			Some(vec![
+				comment::Arrow::new(Green, square("d2"), square("d4")),
+				comment::Arrow::new(Red, square("e7"), square("e5"))
+			])
+		);
+		assert_eq!(
+			comment::parse_highlights("Rd4,Ya1,Bh8"),
+			Some(vec![
+				comment::Highlight::new(Red, square("d4")),
+				comment::Highlight::new(Yellow, square("a1")),
+				comment::Highlight::new(Blue, square("h8"))
+			])
+		);
+
+		assert_eq!(comment::parse_arrows(""), None);
+		assert_eq!(comment::parse_arrows("Xd2d4"), None);
+		assert_eq!(comment::parse_arrows("Gd2d9"), None);
+		assert_eq!(comment::parse_arrows("Gd2d4,"), None);
+		assert_eq!(comment::parse_arrows("Gd2"), None);
+		assert_eq!(comment::parse_highlights("Rd4d5"), None);
+		assert_eq!(comment::parse_highlights("R"), None);
+
+		for s in ["Gd2d4,Re7e5", "Ba1h8"] {
+			let arrows = comment::parse_arrows(s).unwrap();
+			assert_eq!(comment::arrows_to_string(&arrows), s);
+		}
+		for s in ["Rd4", "Gd5,Ye4"] {
+			let highlights = comment::parse_highlights(s).unwrap();
+			assert_eq!(comment::highlights_to_string(&highlights), s);
+		}
+	}
+
+	#[test]
+	fn markup_0000() {
+		use comment::MarkupColor::*;
+
+		let g = make_game("sample_games/markup_0000.pgn".to_string());
+		let c = &g.get_comments()[0];
+		assert_eq!(c.get_arrows().unwrap().len(), 2);
+		assert_eq!(c.get_highlights(), Some(vec![comment::Highlight::new(Red, square("d4"))]));
+		assert_eq!(c.get_text(), "Plan");
+
+		let c = &g.get_next_move().as_ref().unwrap().get_comments()[0];
+		assert_eq!(c.get_arrows(), None);
+		assert_eq!(
+			c.get_highlights(),
+			Some(vec![
+				comment::Highlight::new(Green, square("d5")),
+				comment::Highlight::new(Yellow, square("e4"))
+			])
+		);
+
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(&g),
+			"1. d4 { [%cal Gd2d4,Re7e5] [%csl Rd4] Plan } 1... d5 { [%csl Gd5,Ye4] } *"
+		);
+	}
+
+	#[test]
+	fn set_markup() {
+		let mut c = comment::Comment::new();
+		c.set_arrows(&[comment::Arrow::new(comment::MarkupColor::Blue, square("g1"), square("f3"))]);
+		c.set_highlights(&[comment::Highlight::new(comment::MarkupColor::Green, square("f3"))]);
+		c.set_text("Develop".to_string());
+
+		let mut g = game::GameTree::new();
+		g.set_move_text("Nf3".to_string(), &pgn_tokenizer::Side::White, 1);
+		g.add_comment(c);
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(&g),
+			"1. Nf3 { [%cal Bg1f3] [%csl Gf3] Develop }"
+		);
+	}
+
+}
@@ -0,0 +1,163 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use pgn_parser::game;
+	use pgn_parser::game_cursor;
+	use pgn_parser::game_cursor::{GameCursor, NodePath};
+	use pgn_parser::pgn_tree_builder;
+	use pgn_parser::pgn_tokenizer;
+
+	fn make_game(file: String) -> game::GameTree {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.build_game_tree(0).unwrap().unwrap()
+	}
+
+	fn path(s: &str) -> NodePath {
+		game_cursor::parse_node_path(s).unwrap()
+	}
+
+	#[test]
+	fn paths() {
+		let mut p = NodePath::from_main_line(12);
+		p.push_variation(2, 3);
+		assert_eq!(p.to_string(), "12/2.3");
+		assert_eq!(p.get_ply(), 15);
+		assert!(!p.is_main_line());
+		assert_eq!(path("12/2.3"), p);
+		assert_eq!(path("0"), NodePath::new());
+
+		assert_eq!(game_cursor::parse_node_path(""), None);
+		assert_eq!(game_cursor::parse_node_path("1/2"), None);
+		assert_eq!(game_cursor::parse_node_path("1/a.2"), None);
+		assert_eq!(game_cursor::parse_node_path("-1"), None);
+	}
+
+	#[test]
+	fn get_node() {
+		let g = make_game("sample_games/cursor_0000.pgn".to_string());
+
+		assert_eq!(g.get_node(&path("0")).unwrap().get_move_text(), "e4");
+		assert_eq!(g.get_node(&path("3")).unwrap().get_move_text(), "Nc6");
+		assert_eq!(g.get_node(&path("2/0.0")).unwrap().get_move_text(), "f4");
+		assert_eq!(g.get_node(&path("2/0.2")).unwrap().get_move_text(), "Nf3");
+		assert_eq!(g.get_node(&path("2/0.1/0.1")).unwrap().get_move_text(), "exd5");
+		assert_eq!(g.get_node(&path("2/1.0")).unwrap().get_move_text(), "Nc3");
+
+		assert!(g.get_node(&path("2/2.0")).is_none());
+		assert!(g.get_node(&path("2/1.1")).is_none());
+		assert!(g.get_node(&path("9")).is_none());
+	}
+
+	#[test]
+	fn cursor_movement() {
+		let g = make_game("sample_games/cursor_0000.pgn".to_string());
+		let mut c = GameCursor::new(&g);
+		assert_eq!(c.get_node().get_move_text(), "e4");
+		assert!(!c.back());
+		assert!(!c.exit_variation());
+
+		assert!(c.forward());
+		assert!(c.forward());
+		assert_eq!(c.get_node().get_move_text(), "Nf3");
+		assert!(!c.enter_variation(2));
+
+		assert!(c.enter_variation(0));
+		assert!(c.is_in_variation());
+		assert_eq!(c.get_node().get_move_text(), "f4");
+		assert!(c.forward());
+		assert!(c.enter_variation(0));
+		assert!(c.forward());
+		assert_eq!(c.get_node().get_move_text(), "exd5");
+		assert_eq!(c.get_path(), &path("2/0.1/0.1"));
+		assert_eq!(c.get_ply(), 4);
+		assert!(!c.forward());
+
+		assert!(c.back());
+		assert!(!c.back());
+		assert_eq!(c.get_node().get_move_text(), "d5");
+		assert!(c.exit_variation());
+		assert_eq!(c.get_node().get_move_text(), "exf4");
+		assert!(c.exit_variation());
+		assert!(!c.is_in_variation());
+		assert_eq!(c.get_node().get_move_text(), "Nf3");
+		assert_eq!(c.get_path(), &path("2"));
+
+		// the termination marker is not a move
+		assert!(c.forward());
+		assert!(c.forward());
+		assert_eq!(c.get_node().get_move_text(), "Bb5");
+		assert!(!c.forward());
+	}
+
+	#[test]
+	fn cursor_jumps() {
+		let g = make_game("sample_games/cursor_0000.pgn".to_string());
+		let mut c = GameCursor::new(&g);
+
+		assert!(c.go_to_ply(3));
+		assert_eq!(c.get_node().get_move_text(), "Nc6");
+		assert!(!c.go_to_ply(5));
+		assert_eq!(c.get_node().get_move_text(), "Nc6");
+
+		// a path can be stored and resolved later
+		assert!(c.go_to(&path("2/0.1/0.1")));
+		let stored = c.get_path().to_string();
+		assert!(c.go_to_ply(0));
+		assert!(c.go_to(&path(&stored)));
+		assert_eq!(c.get_node().get_move_text(), "exd5");
+
+		// the moves the cursor jumped over can be visited
+		assert!(c.back());
+		assert!(c.exit_variation());
+		assert!(c.back());
+		assert_eq!(c.get_node().get_move_text(), "f4");
+		assert!(c.exit_variation());
+		assert!(c.back());
+		assert_eq!(c.get_node().get_move_text(), "e5");
+
+		assert!(!c.go_to(&path("2/0.5")));
+		assert_eq!(c.get_node().get_move_text(), "e5");
+
+		for p in ["0", "3", "2/0.0", "2/0.2", "2/0.1/0.1", "2/1.0"] {
+			assert!(c.go_to(&path(p)));
+			assert!(std::ptr::eq(c.get_node(), g.get_node(&path(p)).unwrap()));
+		}
+	}
+
+}
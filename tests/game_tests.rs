@@ -0,0 +1,194 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use pgn_parser::game;
+	use pgn_parser::game_cursor;
+	use pgn_parser::pgn_formatter;
+	use pgn_parser::pgn_tree_builder;
+	use pgn_parser::pgn_tokenizer;
+
+	fn make_game(file: String) -> game::Game {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.build_game().unwrap().unwrap()
+	}
+
+	fn path(s: &str) -> game_cursor::NodePath {
+		game_cursor::parse_node_path(s).unwrap()
+	}
+
+	fn to_string(g: &game::GameTree) -> String {
+		pgn_formatter::PgnFormatter::new().to_string(g)
+	}
+
+	// 1. e4 e5 2. Nf3 (2. f4 exf4 (2... d5 3. exd5) 3. Nf3) (2. Nc3) 2... Nc6 3. Bb5 *
+	fn cursor_0000() -> game::Game {
+		make_game("sample_games/cursor_0000.pgn".to_string())
+	}
+
+	#[test]
+	fn promote_variation() {
+		let mut g = cursor_0000();
+		let tree = g.get_tree_mut();
+
+		assert!(!tree.get_node_mut(&path("2")).unwrap().promote_variation(2));
+		assert!(tree.get_node_mut(&path("2")).unwrap().promote_variation(1));
+		assert_eq!(
+			to_string(tree),
+			"1. e4 e5 2. Nc3 (2. f4 exf4 (2... d5 3. exd5) 3. Nf3) (2. Nf3 Nc6 3. Bb5) *"
+		);
+
+		// promoting back restores the game
+		assert!(tree.get_node_mut(&path("2")).unwrap().promote_variation(1));
+		assert_eq!(to_string(tree), to_string(cursor_0000().get_tree()));
+	}
+
+	#[test]
+	fn promote_to_main_line() {
+		let mut g = cursor_0000();
+		let tree = g.get_tree_mut();
+
+		assert_eq!(tree.promote_to_main_line(&path("2/0.5")), None);
+		assert_eq!(tree.promote_to_main_line(&path("2/0.1/0.1")), Some(path("4")));
+		assert_eq!(tree.get_node(&path("4")).unwrap().get_move_text(), "exd5");
+		assert_eq!(
+			to_string(tree),
+			"1. e4 e5 2. f4 (2. Nf3 Nc6 3. Bb5) (2. Nc3) 2... d5 (2... exf4 3. Nf3) 3. exd5 *"
+		);
+	}
+
+	#[test]
+	fn remove_and_insert_variations() {
+		let mut g = cursor_0000();
+		let tree = g.get_tree_mut();
+
+		let node = tree.get_node_mut(&path("2")).unwrap();
+		let removed = node.remove_variation(0).unwrap();
+		assert_eq!(removed.get_move_text(), "f4");
+		assert!(node.remove_variation(1).is_none());
+		assert_eq!(to_string(tree), "1. e4 e5 2. Nf3 (2. Nc3) 2... Nc6 3. Bb5 *");
+
+		let node = tree.get_node_mut(&path("2")).unwrap();
+		assert!(!node.insert_variation(5, game::GameTree::new()));
+		assert!(node.insert_variation(1, removed));
+		assert_eq!(
+			to_string(tree),
+			"1. e4 e5 2. Nf3 (2. Nc3) (2. f4 exf4 (2... d5 3. exd5) 3. Nf3) 2... Nc6 3. Bb5 *"
+		);
+	}
+
+	#[test]
+	fn truncate() {
+		let mut g = cursor_0000();
+		let tree = g.get_tree_mut();
+
+		let rest = tree.get_node_mut(&path("2/0.0")).unwrap().truncate().unwrap();
+		assert_eq!(rest.get_move_text(), "exf4");
+		assert!(tree.get_node_mut(&path("2/0.0")).unwrap().truncate().is_none());
+
+		let rest = tree.get_node_mut(&path("3")).unwrap().truncate().unwrap();
+		assert_eq!(rest.get_move_text(), "Bb5");
+		assert_eq!(to_string(tree), "1. e4 e5 2. Nf3 (2. f4) (2. Nc3) 2... Nc6");
+	}
+
+	#[test]
+	fn insert_move() {
+		let mut g = make_game("sample_games/0003-r.pgn".to_string());
+		let tree = g.get_tree_mut();
+		assert_eq!(to_string(tree), "1. d4 (1. e4 e5) 1... d5 1/2-1/2");
+
+		let inserted = tree.insert_move_after("Nf6".to_string()).unwrap();
+		assert_eq!(inserted.get_move_number(), &1);
+		assert_eq!(inserted.get_side(), &Some(pgn_tokenizer::Side::Black));
+
+		// the move that followed becomes a variation
+		let variation = tree.get_node(&path("1/0.0")).unwrap();
+		assert_eq!(variation.get_move_text(), "d5");
+		assert_eq!(variation.get_move_number(), &1);
+		assert_eq!(variation.get_side(), &Some(pgn_tokenizer::Side::Black));
+		assert_eq!(to_string(tree), "1. d4 (1. e4 e5) 1... Nf6 (1... d5) 1/2-1/2");
+
+		// in a variation
+		let variation = tree.get_node_mut(&path("0/0.0")).unwrap();
+		variation.insert_move_after("c5".to_string()).unwrap().insert_move_after("Nf3".to_string());
+		assert_eq!(to_string(tree), "1. d4 (1. e4 c5 (1... e5) 2. Nf3) 1... Nf6 (1... d5) 1/2-1/2");
+
+		// before the termination marker
+		let last = tree.get_node_mut(&path("1")).unwrap();
+		last.insert_move_after("c4".to_string()).unwrap();
+		assert_eq!(to_string(tree), "1. d4 (1. e4 c5 (1... e5) 2. Nf3) 1... Nf6 (1... d5) 2. c4 1/2-1/2");
+	}
+
+	#[test]
+	fn insert_move_past_last_number() {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize("[FEN \"8/8/8/8/8/8/8/K6k b - - 0 65535\"] 65535... Kg1 *".to_string());
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let mut g = builder.build_game().unwrap().unwrap();
+
+		assert!(g.get_tree_mut().insert_move_after("Kb1".to_string()).is_none());
+		assert_eq!(to_string(g.get_tree()), "65535... Kg1 *");
+	}
+
+	#[test]
+	fn insert_move_keeps_replay() {
+		let mut g = make_game("sample_games/0003-r.pgn".to_string());
+		assert_eq!(g.replay_moves(), vec![]);
+
+		g.get_tree_mut().insert_move_after("Nf6".to_string()).unwrap();
+		assert!(g.get_tree().get_node(&path("1")).unwrap().get_position().is_none());
+		assert!(g.get_tree().get_node(&path("1/0.0")).unwrap().get_position().is_some());
+
+		assert_eq!(g.replay_moves(), vec![]);
+		assert!(g.get_tree().get_node(&path("1")).unwrap().get_position().is_some());
+	}
+
+	#[test]
+	fn promote_keeps_pre_comments() {
+		let mut g = make_game("sample_games/comment_0001.pgn".to_string());
+		let tree = g.get_tree_mut();
+
+		assert!(tree.get_node_mut(&path("2")).unwrap().promote_variation(0));
+		assert_eq!(
+			to_string(tree),
+			"{ Annotated by Alice } 1. e4 e5 2. f4 ({ Better was } 2. Nf3 Nc6) \
+			({ the quiet move } 2. Nc3) 2... exf4 *"
+		);
+	}
+}
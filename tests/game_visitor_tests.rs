@@ -0,0 +1,193 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use pgn_parser::comment;
+	use pgn_parser::game;
+	use pgn_parser::game_visitor;
+	use pgn_parser::game_visitor::GameVisitor;
+	use pgn_parser::nag;
+	use pgn_parser::pgn_tree_builder;
+	use pgn_parser::pgn_tokenizer;
+	use pgn_parser::pgn_tokenizer::{ResultType, Side};
+
+	// Writes down every callback it receives.
+	#[derive(Default)]
+	struct Recorder {
+		m_events: Vec<String>
+	}
+
+	impl GameVisitor for Recorder {
+		fn begin_game(&mut self) { self.m_events.push("begin".to_string()); }
+		fn end_game(&mut self) { self.m_events.push("end".to_string()); }
+		fn tag(&mut self, tag: &game::TagType, value: &str) {
+			self.m_events.push(format!("tag {} {}", game::tag_to_string(tag), value));
+		}
+		fn game_move(&mut self, move_number: u16, side: Side, text: &str) {
+			let dots = if side == Side::White { "." } else { "..." };
+			self.m_events.push(format!("{}{} {}", move_number, dots, text));
+		}
+		fn comment(&mut self, c: &comment::Comment) {
+			self.m_events.push(format!("{{{}}}", c.get_text()));
+		}
+		fn nag(&mut self, n: &nag::Nag) { self.m_events.push(n.to_numeric_string()); }
+		fn begin_variation(&mut self) { self.m_events.push("(".to_string()); }
+		fn end_variation(&mut self) { self.m_events.push(")".to_string()); }
+		fn result(&mut self, r: ResultType) {
+			self.m_events.push(pgn_tokenizer::result_to_string(&r));
+		}
+	}
+
+	// Counts the moves and nothing else.
+	#[derive(Default)]
+	struct MoveCounter {
+		m_moves: usize
+	}
+
+	impl GameVisitor for MoveCounter {
+		fn game_move(&mut self, _move_number: u16, _side: Side, _text: &str) {
+			self.m_moves += 1;
+		}
+	}
+
+	fn visit_tree(file: &str) -> Vec<String> {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file.to_string()).expect("Failed to open file");
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+
+		let mut r = Recorder::default();
+		for g in builder.build_games().into_iter() {
+			game_visitor::visit_game(&g.unwrap(), &mut r);
+		}
+		r.m_events
+	}
+
+	fn visit_tokens(file: &str) -> Vec<String> {
+		let (all_tokens, all_token_types, _) =
+			pgn_tokenizer::tokenize_file(file.to_string()).expect("Failed to open file");
+
+		let mut r = Recorder::default();
+		game_visitor::visit_tokens(&all_tokens, &all_token_types, &mut r);
+		r.m_events
+	}
+
+	#[test]
+	fn variations() {
+		let file = "sample_games/cursor_0000.pgn";
+		let expected = vec![
+			"begin",
+			"1. e4", "1... e5", "2. Nf3",
+			"(", "2. f4", "2... exf4",
+				"(", "2... d5", "3. exd5", ")",
+				"3. Nf3", ")",
+			"(", "2. Nc3", ")",
+			"2... Nc6", "3. Bb5", "*",
+			"end"
+		];
+		assert_eq!(visit_tree(file), expected);
+		assert_eq!(visit_tokens(file), expected);
+	}
+
+	#[test]
+	fn nags_and_comments() {
+		let file = "sample_games/nag_0000.pgn";
+		let expected = vec![
+			"begin",
+			"1. e4", "$1", "1... e5", "$6",
+			"2. Nf3", "$14", "2... Nc6", "$1", "$18",
+			"3. Bb5", "$18", "(", "3. Bc4", "$5", "3... Bc5", "$200", ")",
+			"3... a6", "$4", "{Is this good? 1-0}",
+			"1-0",
+			"end"
+		];
+		assert_eq!(visit_tree(file), expected);
+		assert_eq!(visit_tokens(file), expected);
+	}
+
+	#[test]
+	fn several_games() {
+		let file = "sample_games/multi_0001.pgn";
+		let expected = vec![
+			"begin", "tag Event First", "1. d4", "1... d5", "end",
+			"begin", "tag Event Second", "1. e4", "1... e5", "1/2-1/2", "end"
+		];
+		assert_eq!(visit_tree(file), expected);
+		assert_eq!(visit_tokens(file), expected);
+	}
+
+	#[test]
+	fn comments_before_moves() {
+		let file = "sample_games/comment_0001.pgn";
+		let expected = vec![
+			"begin", "tag Event Comments before moves",
+			"{Annotated by Alice}", "1. e4", "1... e5", "2. Nf3",
+			"(", "{Better was}", "2. f4", "2... exf4", ")",
+			"(", "{the quiet move}", "2. Nc3", ")",
+			"2... Nc6", "*",
+			"end"
+		];
+		assert_eq!(visit_tree(file), expected);
+		assert_eq!(visit_tokens(file), expected);
+	}
+
+	#[test]
+	fn initial_position() {
+		let file = "sample_games/fen_0000.pgn";
+		assert_eq!(visit_tree(file), visit_tokens(file));
+	}
+
+	#[test]
+	fn default_callbacks() {
+		let (all_tokens, all_token_types, _) =
+			pgn_tokenizer::tokenize_file("sample_games/full_game_ii.pgn".to_string())
+			.expect("Failed to open file");
+
+		let mut counter = MoveCounter::default();
+		game_visitor::visit_tokens(&all_tokens, &all_token_types, &mut counter);
+		assert!(counter.m_moves > 0);
+	}
+
+	#[test]
+	fn last_move_number() {
+		let (all_tokens, all_token_types, _) =
+			pgn_tokenizer::tokenize("65535... Kh2 Ka2 (Kb2) *".to_string());
+
+		let mut r = Recorder::default();
+		game_visitor::visit_tokens(&all_tokens, &all_token_types, &mut r);
+		// there is no number for the move after the last one
+		assert_eq!(r.m_events, vec!["begin", "65535... Kh2", "(", ")", "*", "end"]);
+	}
+}
@@ -0,0 +1,328 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use pgn_parser::game;
+	use pgn_parser::pgn_error;
+	use pgn_parser::pgn_error::PgnErrorKind;
+	use pgn_parser::pgn_formatter;
+	use pgn_parser::pgn_tree_builder;
+	use pgn_parser::pgn_tokenizer;
+
+	fn make_builder(file: String) -> pgn_tree_builder::PGNTreeBuilder {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
+		
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+
+		builder
+	}
+
+	fn make_builder_from(text: &str) -> pgn_tree_builder::PGNTreeBuilder {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize(text.to_string());
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+
+		builder
+	}
+
+	fn make_error(file: String) -> pgn_error::PgnError {
+		let mut builder = make_builder(file);
+		builder.build_game_tree(0).unwrap_err()
+	}
+
+	fn make_lenient_game(file: String) -> game::Game {
+		let mut builder = make_builder(file);
+		builder.set_options(*pgn_tree_builder::ParseOptions::new().set_strict(false));
+		builder.build_game().unwrap().unwrap()
+	}
+
+	// The game parsed in lenient mode and the kinds of its warnings.
+	fn recover(file: &str) -> (String, Vec<PgnErrorKind>) {
+		let g = make_lenient_game(file.to_string());
+		let kinds = g.get_warnings().iter().map(|w| w.get_kind().clone()).collect();
+		(pgn_formatter::PgnFormatter::new().to_string(g.get_tree()), kinds)
+	}
+
+	fn unexpected(token: &str) -> PgnErrorKind {
+		PgnErrorKind::UnexpectedToken { token: token.to_string() }
+	}
+
+	#[test]
+	fn error_0000() {
+		let err = make_error("sample_games/error_0000.pgn".to_string());
+		assert_eq!(
+			err.get_kind(),
+			&PgnErrorKind::MoveNumberMismatch {
+				expected_id: 1,
+				expected_side: pgn_tokenizer::Side::Black,
+				found_id: 2,
+				found_side: pgn_tokenizer::Side::White
+			}
+		);
+		assert_eq!(err.get_token_index(), 2);
+		assert_eq!(err.get_span(), &Some(pgn_tokenizer::Span::new(6, 2, 1, 7)));
+	}
+
+	#[test]
+	fn error_0001() {
+		let err = make_error("sample_games/error_0001.pgn".to_string());
+		assert_eq!(err.get_kind(), &PgnErrorKind::UnbalancedVariation);
+		assert_eq!(err.get_token_index(), 8);
+		assert_eq!(err.get_span(), &None);
+	}
+
+	#[test]
+	fn error_0002() {
+		let err = make_error("sample_games/error_0002.pgn".to_string());
+		assert_eq!(err.get_kind(), &PgnErrorKind::UnbalancedVariation);
+		assert_eq!(err.get_token_index(), 3);
+	}
+
+	#[test]
+	fn error_0003() {
+		let err = make_error("sample_games/error_0003.pgn".to_string());
+		assert_eq!(err.get_kind(), &PgnErrorKind::UnterminatedComment);
+		assert_eq!(err.get_token_index(), 4);
+	}
+
+	#[test]
+	fn error_0004() {
+		let err = make_error("sample_games/error_0004.pgn".to_string());
+		assert_eq!(err.get_kind(), &PgnErrorKind::UnterminatedCommentTag);
+		// the error is found in the body of the comment
+		assert_eq!(err.get_token_index(), 3);
+		assert_eq!(err.get_span(), &Some(pgn_tokenizer::Span::new(7, 12, 1, 8)));
+	}
+
+	#[test]
+	fn error_0005() {
+		let err = make_error("sample_games/error_0005.pgn".to_string());
+		assert_eq!(
+			err.get_kind(),
+			&PgnErrorKind::UnexpectedToken { token: ")".to_string() }
+		);
+		assert_eq!(err.get_token_index(), 3);
+	}
+
+	#[test]
+	fn error_0006() {
+		let err = make_error("sample_games/error_0006.pgn".to_string());
+		assert_eq!(err.get_kind(), &PgnErrorKind::UnbalancedVariation);
+		assert_eq!(err.get_token_index(), 5);
+	}
+
+	#[test]
+	fn multi_0002() {
+		let mut builder = make_builder("sample_games/multi_0002.pgn".to_string());
+		let games: Vec<Result<game::Game, pgn_error::PgnError>> = builder.build_games();
+		assert_eq!(games.len(), 4);
+
+		assert!(matches!(
+			games[0].as_ref().err().unwrap().get_kind(),
+			PgnErrorKind::MoveNumberMismatch { .. }
+		));
+
+		let g = games[1].as_ref().unwrap().get_tree();
+		assert_eq!(g.get_move_text(), "e4");
+		assert_eq!(g.get_comments().len(), 1);
+
+		assert!(matches!(
+			games[2].as_ref().err().unwrap().get_kind(),
+			PgnErrorKind::MoveNumberMismatch { .. }
+		));
+
+		let g = games[3].as_ref().unwrap().get_tree();
+		assert_eq!(g.get_move_text(), "Nf3");
+		assert!(g.get_next_move().as_ref().unwrap().is_result());
+	}
+
+	#[test]
+	fn error_0007() {
+		let mut builder = make_builder("sample_games/error_0007.pgn".to_string());
+		let err = builder.build_game().err().unwrap();
+		assert_eq!(
+			err.get_kind(),
+			&PgnErrorKind::InvalidFen { fen: "8/8/8 w - - 0 1".to_string() }
+		);
+		assert_eq!(err.get_token_index(), 6);
+		assert_eq!(err.get_span(), &Some(pgn_tokenizer::Span::new(17, 17, 2, 6)));
+	}
+
+	#[test]
+	fn lenient_errors() {
+		assert_eq!(
+			recover("sample_games/error_0000.pgn"),
+			(
+				"1. d4 c4".to_string(),
+				vec![PgnErrorKind::MoveNumberMismatch {
+					expected_id: 1,
+					expected_side: pgn_tokenizer::Side::Black,
+					found_id: 2,
+					found_side: pgn_tokenizer::Side::White
+				}]
+			)
+		);
+		assert_eq!(
+			recover("sample_games/error_0001.pgn"),
+			("1. d4 (1. e4 e5 2. Nf3)".to_string(), vec![PgnErrorKind::UnbalancedVariation])
+		);
+		assert_eq!(
+			recover("sample_games/error_0002.pgn"),
+			("1. d4 d5 2. c4".to_string(), vec![PgnErrorKind::UnbalancedVariation])
+		);
+		assert_eq!(
+			recover("sample_games/error_0003.pgn"),
+			("1. d4 { A A }".to_string(), vec![PgnErrorKind::UnterminatedComment])
+		);
+		assert_eq!(
+			recover("sample_games/error_0004.pgn"),
+			(
+				"1. d4 { [%clk 0:01 } 1... d5".to_string(),
+				vec![PgnErrorKind::UnterminatedCommentTag, unexpected("d5")]
+			)
+		);
+		assert_eq!(
+			recover("sample_games/error_0005.pgn"),
+			("1. d4 d5".to_string(), vec![unexpected(")")])
+		);
+		assert_eq!(
+			recover("sample_games/error_0006.pgn"),
+			(
+				"1. d4 (1. e4) 1... d5".to_string(),
+				vec![PgnErrorKind::UnbalancedVariation, unexpected("d5")]
+			)
+		);
+
+		// the line that contains the variation goes on after its last move
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize("1. e4 (1. d4 2.) (1. c4) 1... e5 *".to_string());
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.set_options(*pgn_tree_builder::ParseOptions::new().set_strict(false));
+		let g = builder.build_game().unwrap().unwrap();
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(g.get_tree()),
+			"1. e4 (1. d4) (1. c4) 1... e5 *"
+		);
+		let kinds: Vec<PgnErrorKind> = g.get_warnings().iter().map(|w| w.get_kind().clone()).collect();
+		assert_eq!(
+			kinds,
+			vec![
+				PgnErrorKind::MoveNumberMismatch {
+					expected_id: 1,
+					expected_side: pgn_tokenizer::Side::Black,
+					found_id: 2,
+					found_side: pgn_tokenizer::Side::White
+				},
+				unexpected(")")
+			]
+		);
+	}
+
+	#[test]
+	fn lenient_0000() {
+		let mut g = make_lenient_game("sample_games/lenient_0000.pgn".to_string());
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(g.get_tree()),
+			"1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 (6. d3 b5) 6... b5 *"
+		);
+		assert_eq!(g.replay_moves(), vec![]);
+
+		let warnings = g.get_warnings();
+		assert_eq!(warnings.len(), 6);
+		assert!(matches!(
+			warnings[0].get_kind(),
+			PgnErrorKind::MoveNumberMismatch { expected_id: 3, found_id: 4, .. }
+		));
+		assert_eq!(warnings[0].get_span(), &Some(pgn_tokenizer::Span::new(50, 2, 3, 21)));
+		assert_eq!(warnings[1].get_kind(), &unexpected("]"));
+		assert!(matches!(
+			warnings[2].get_kind(),
+			PgnErrorKind::MoveNumberMismatch { expected_id: 5, found_id: 5, .. }
+		));
+		assert_eq!(warnings[3].get_kind(), &unexpected(")"));
+		assert_eq!(warnings[4].get_kind(), &PgnErrorKind::UnbalancedVariation);
+		assert_eq!(warnings[5].get_kind(), &unexpected("b5"));
+
+		// the same game fails in strict mode
+		let mut builder = make_builder("sample_games/lenient_0000.pgn".to_string());
+		assert!(builder.build_game().is_err());
+	}
+
+	#[test]
+	fn lenient_without_errors() {
+		let g = make_lenient_game("sample_games/cursor_0000.pgn".to_string());
+		assert_eq!(g.get_warnings().len(), 0);
+	}
+
+	#[test]
+	fn large_move_number() {
+		let mut builder = make_builder_from("100000. e4 *");
+		let err = builder.build_game().err().unwrap();
+		assert_eq!(err.get_kind(), &unexpected("100000."));
+		assert_eq!(err.get_token_index(), 0);
+	}
+
+	#[test]
+	fn fen_with_long_digit_run() {
+		let fen = "99999999999999999999999999999999/8/8/8/8/8/8/8 w - - 0 1";
+		let mut builder = make_builder_from(&format!("[FEN \"{}\"] *", fen));
+		let err = builder.build_game().err().unwrap();
+		assert_eq!(err.get_kind(), &PgnErrorKind::InvalidFen { fen: fen.to_string() });
+	}
+
+	#[test]
+	fn move_number_overflow() {
+		let mut builder = make_builder_from("[FEN \"8/8/8/8/8/8/8/K6k b - - 0 65535\"] 65535... Kh2 *");
+		let g = builder.build_game().unwrap().unwrap();
+		assert_eq!(g.get_tree().get_move_number(), &65535);
+
+		let text = "[FEN \"8/8/8/8/8/8/8/K6k b - - 0 65535\"] 65535... Kh2 Ka2 *";
+		let mut builder = make_builder_from(text);
+		let err = builder.build_game().err().unwrap();
+		assert_eq!(err.get_kind(), &PgnErrorKind::MoveNumberOverflow);
+		assert_eq!(err.get_token_index(), 6);
+
+		let mut builder = make_builder_from(text);
+		builder.set_options(*pgn_tree_builder::ParseOptions::new().set_strict(false));
+		assert_eq!(
+			builder.build_game().err().unwrap().get_kind(),
+			&PgnErrorKind::MoveNumberOverflow
+		);
+	}
+}
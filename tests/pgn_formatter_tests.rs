@@ -38,28 +38,19 @@ mod tests {
 	use pgn_parser::pgn_tree_builder;
 	use pgn_parser::pgn_tokenizer;
 
-	use std::io::BufRead;
-
 	fn make_builder(file: String) -> pgn_tree_builder::PGNTreeBuilder {
-		let mut entire_file_str = String::new();
-
-		let file = std::fs::File::open(file).expect("Failed to open file");
-		let reader = std::io::BufReader::new(file);
-		for line in reader.lines() {
-			entire_file_str.push_str( line.unwrap().trim() );
-		}
-		
-		let (all_tokens, all_token_types) = pgn_tokenizer::tokenize(entire_file_str);
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
 		
 		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
-		builder.set_token_list(all_tokens, all_token_types);
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
 
 		builder
 	}
 
 	fn make_game(file: String) -> game::GameTree {
 		let mut builder = make_builder(file);
-		if let Some(g) = builder.build_game_tree(0) {
+		if let Ok(Some(g)) = builder.build_game_tree(0) {
 			return g;
 		}
 
@@ -84,9 +75,9 @@ mod tests {
 
 	#[test]
 	fn sample_0000() {
-		for pv in vec![false, true] {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pv in [false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0000.pgn".to_string(), pv, pr, pc),
 				"1. d4".to_string()
@@ -97,8 +88,8 @@ mod tests {
 	}
 	#[test]
 	fn sample_0000_r() {
-		for pc in vec![false, true] {
-		for pv in vec![false, true] {
+		for pc in [false, true] {
+		for pv in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0000-r.pgn".to_string(), pv, true, pc),
 				"1. d4 1-0".to_string()
@@ -113,9 +104,9 @@ mod tests {
 
 	#[test]
 	fn sample_0001() {
-		for pv in vec![false, true] {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pv in [false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0001.pgn".to_string(), pv, pr, pc),
 				"1. d4 d5".to_string()
@@ -126,8 +117,8 @@ mod tests {
 	}
 	#[test]
 	fn sample_0001_r() {
-		for pv in vec![false, true] {
-		for pc in vec![false, true] {
+		for pv in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0001-r.pgn".to_string(), pv, true, pc),
 				"1. d4 d5 0-1".to_string()
@@ -142,8 +133,8 @@ mod tests {
 
 	#[test]
 	fn sample_0002() {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0002.pgn".to_string(), true, pr, pc),
 				"1. d4 (1. e4) 1... d5".to_string()
@@ -157,7 +148,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0002_r() {
-		for pc in vec![false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0002-r.pgn".to_string(), true, true, pc),
 				"1. d4 (1. e4) 1... d5 0-1".to_string()
@@ -180,8 +171,8 @@ mod tests {
 
 	#[test]
 	fn sample_0003() {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0003.pgn".to_string(), true, pr, pc),
 				"1. d4 (1. e4 e5) 1... d5".to_string()
@@ -195,7 +186,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0003_r() {
-		for pc in vec![false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0003-r.pgn".to_string(), true, true, pc),
 				"1. d4 (1. e4 e5) 1... d5 1/2-1/2".to_string()
@@ -218,8 +209,8 @@ mod tests {
 
 	#[test]
 	fn sample_0004() {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0004.pgn".to_string(), true, pr, pc),
 				"1. d4 (1. e4) (1. f4) 1... d5".to_string()
@@ -233,7 +224,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0004_r() {
-		for pc in vec![false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0004-r.pgn".to_string(), true, true, pc),
 				"1. d4 (1. e4) (1. f4) 1... d5 1/2-1/2".to_string()
@@ -256,8 +247,8 @@ mod tests {
 
 	#[test]
 	fn sample_0005() {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0005.pgn".to_string(), true, pr, pc),
 				"1. d4 (1. e4 e5) (1. f4) 1... d5".to_string()
@@ -271,7 +262,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0005_r() {
-		for pc in vec![false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0005-r.pgn".to_string(), true, true, pc),
 				"1. d4 (1. e4 e5) (1. f4) 1... d5 0-1".to_string()
@@ -294,8 +285,8 @@ mod tests {
 
 	#[test]
 	fn sample_0006() {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0006.pgn".to_string(), true, pr, pc),
 				"1. d4 (1. e4 e5) (1. f4 Cc6) 1... d5".to_string()
@@ -309,7 +300,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0006_r() {
-		for pc in vec![false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0006-r.pgn".to_string(), true, true, pc),
 				"1. d4 (1. e4 e5) (1. f4 Cc6) 1... d5 1-0".to_string()
@@ -332,8 +323,8 @@ mod tests {
 
 	#[test]
 	fn sample_0007() {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0007.pgn".to_string(), true, pr, pc),
 				"1. d4 d5 2. c4 c6".to_string()
@@ -347,8 +338,8 @@ mod tests {
 	}
 	#[test]
 	fn sample_0007_r() {
-		for pv in vec![false, true] {
-		for pc in vec![false, true] {
+		for pv in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0007-r.pgn".to_string(), pv, true, pc),
 				"1. d4 d5 2. c4 c6 1/2-1/2".to_string()
@@ -363,8 +354,8 @@ mod tests {
 
 	#[test]
 	fn sample_0008() {
-		for pr in vec![false, true] {
-		for pc in vec![false, true] {
+		for pr in [false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0008.pgn".to_string(), true, pr, pc),
 				"1. d4 d5 2. c4 c6 (2... e5)".to_string()
@@ -378,7 +369,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0008_r() {
-		for pc in vec![false, true] {
+		for pc in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0008-r.pgn".to_string(), true, true, pc),
 				"1. d4 d5 2. c4 c6 (2... e5) 0-1".to_string()
@@ -402,8 +393,8 @@ mod tests {
 
 	#[test]
 	fn sample_0009() {
-		for pv in vec![false, true] {
-		for pr in vec![false, true] {
+		for pv in [false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0009.pgn".to_string(), pv, pr, true),
 				"1. d4 { This is a bad move }".to_string()
@@ -417,7 +408,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0009_r() {
-		for pv in vec![false, true] {
+		for pv in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0009-r.pgn".to_string(), pv, true, true),
 				"1. d4 { This is a bad move } 1-0".to_string()
@@ -439,8 +430,8 @@ mod tests {
 
 	#[test]
 	fn sample_0010() {
-		for pv in vec![false, true] {
-		for pr in vec![false, true] {
+		for pv in [false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0010.pgn".to_string(), pv, pr, true),
 				"1. d4 { This is a bad move } 1... d5 { [%clk 19] This is also a bad move }".to_string()
@@ -454,7 +445,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0010_r() {
-		for pv in vec![false, true] {
+		for pv in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0010-r.pgn".to_string(), pv, true, true),
 				"1. d4 { This is a bad move } 1... d5 { [%clk 19] This is also a bad move } 0-1".to_string()
@@ -476,7 +467,7 @@ mod tests {
 
 	#[test]
 	fn sample_0011() {
-		for pr in vec![false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0011.pgn".to_string(), true, pr, true),
 				"1. d4 { [%clk 99] } (1. e4 { [%clk 99] [%eval -50] }) 1... d5".to_string()
@@ -535,7 +526,7 @@ mod tests {
 
 	#[test]
 	fn sample_0012() {
-		for pr in vec![false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0012.pgn".to_string(), true, pr, true),
 				"1. d4 { Hola } (1. e4 { Adéu } 1... e5 { 新しい }) 1... d5 { 大きい }".to_string()
@@ -594,7 +585,7 @@ mod tests {
 
 	#[test]
 	fn sample_0013() {
-		for pr in vec![false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0013.pgn".to_string(), true, pr, true),
 				"1. d4 { [%clk 9] A A } (1. e4 { [%clk 9] B B }) (1. f4 { [%clk 9] C C }) 1... d5".to_string()
@@ -653,7 +644,7 @@ mod tests {
 
 	#[test]
 	fn sample_0014() {
-		for pr in vec![false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0014.pgn".to_string(), true, pr, true),
 				"1. d4 { [%clk 9] [%eval -9] A A } (1. e4 { [%clk 9] [%eval -9] B B } 1... e5 { [%clk 9] [%eval -9] C C }) (1. f4 { [%clk 9] [%eval -9] D D }) 1... d5 { [%clk 9] [%eval -9] E E }".to_string()
@@ -712,7 +703,7 @@ mod tests {
 
 	#[test]
 	fn sample_0015() {
-		for pr in vec![false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0015.pgn".to_string(), true, pr, true),
 				"1. d4 { [%clk 9] [%eval -9] A A } (1. e4 { [%clk 9] [%eval -9] B B } 1... e5 { [%clk 9] [%eval -9] C C }) (1. f4 { [%clk 9] [%eval -9] D D } 1... Cc6) 1... d5 { [%clk 9] [%eval -9] E E }".to_string()
@@ -771,8 +762,8 @@ mod tests {
 
 	#[test]
 	fn sample_0016() {
-		for pv in vec![false, true] {
-		for pr in vec![false, true] {
+		for pv in [false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0016.pgn".to_string(), pv, pr, true),
 				"1. d4 d5 { B B } 2. c4 c6 { C C }".to_string()
@@ -786,7 +777,7 @@ mod tests {
 	}
 	#[test]
 	fn sample_0016_r() {
-		for pv in vec![false, true] {
+		for pv in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0016-r.pgn".to_string(), pv, true, true),
 				"1. d4 d5 { B B } 2. c4 c6 { C C } 1-0".to_string()
@@ -809,7 +800,7 @@ mod tests {
 
 	#[test]
 	fn sample_0017() {
-		for pr in vec![false, true] {
+		for pr in [false, true] {
 			assert_eq!(
 				game_to_string("sample_games/0017.pgn".to_string(), true, pr, true),
 				"1. d4 d5 2. c4 c6 (2... e5 { P P })".to_string()
@@ -866,4 +857,360 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn nag_0000() {
+		let mut builder = make_builder("sample_games/nag_0000.pgn".to_string());
+		let g = builder.build_game_tree(0).unwrap().unwrap();
+
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.to_string(&g),
+			"1. e4! e5?! 2. Nf3 += Nc6! +- 3. Bb5 +- (3. Bc4!? Bc5 $200) \
+			3... a6?? { Is this good? 1-0 } 1-0".to_string()
+		);
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_print_nags_as_symbols(false)
+				.to_string(&g),
+			"1. e4 $1 e5 $6 2. Nf3 $14 Nc6 $1 $18 3. Bb5 $18 (3. Bc4 $5 Bc5 $200) \
+			3... a6 $4 { Is this good? 1-0 } 1-0".to_string()
+		);
+	}
+
+	fn make_games(file: String) -> Vec<game::Game> {
+		let mut builder = make_builder(file);
+		builder.build_games()
+			.into_iter()
+			.map(|g| g.unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn format_game_tags_0000() {
+		let games = make_games("sample_games/tags_0000.pgn".to_string());
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().format_game(&games[0]),
+			"[Event \"Casual game\"]\n\
+			[Site \"Barcelona\"]\n\
+			[Date \"2024.05.01\"]\n\
+			[Round \"1\"]\n\
+			[White \"Alice\"]\n\
+			[Black \"Bob\"]\n\
+			[Result \"1-0\"]\n\
+			[WhiteElo \"2100\"]\n\
+			[Opening \"Queen's Gambit\"]\n\
+			[Mood \"Good\"]\n\
+			\n\
+			1. d4 d5 2. c4 1-0\n".to_string()
+		);
+	}
+
+	#[test]
+	fn format_game_missing_tags() {
+		let games = make_games("sample_games/multi_0000.pgn".to_string());
+		let mut formatter = pgn_formatter::PgnFormatter::new();
+		assert_eq!(
+			formatter.format_game(&games[1]),
+			"[Event \"Second\"]\n\
+			[Site \"?\"]\n\
+			[Date \"????.??.??\"]\n\
+			[Round \"?\"]\n\
+			[White \"?\"]\n\
+			[Black \"?\"]\n\
+			[Result \"0-1\"]\n\
+			\n\
+			1. e4 (1. d4 d5) 1... e5 0-1\n".to_string()
+		);
+
+		// the termination marker is always written
+		formatter.set_print_result(false);
+		assert!(formatter.format_game(&games[1]).ends_with("\n1. e4 (1. d4 d5) 1... e5 0-1\n"));
+	}
+
+	#[test]
+	fn format_game_without_moves() {
+		let mut g = game::Game::new();
+		g.set_tag(game::TagType::Event, "Empty".to_string());
+		g.set_tag(game::TagType::Result, "1/2-1/2".to_string());
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().format_game(&g),
+			"[Event \"Empty\"]\n\
+			[Site \"?\"]\n\
+			[Date \"????.??.??\"]\n\
+			[Round \"?\"]\n\
+			[White \"?\"]\n\
+			[Black \"?\"]\n\
+			[Result \"1/2-1/2\"]\n\
+			\n\
+			1/2-1/2\n".to_string()
+		);
+	}
+
+	#[test]
+	fn format_game_comments_without_moves() {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize("{Intro} 1-0 {Outro}".to_string());
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let g = builder.build_game().unwrap().unwrap();
+
+		let mut formatter = pgn_formatter::PgnFormatter::new();
+		assert!(formatter.format_game(&g).ends_with("\n\n{ Intro } 1-0 { Outro }\n"));
+		formatter.set_print_comments(false);
+		assert!(formatter.format_game(&g).ends_with("\n\n1-0\n"));
+	}
+
+	#[test]
+	fn format_game_round_trip() {
+		for file in [
+			"sample_games/multi_0000.pgn",
+			"sample_games/multi_0003.pgn",
+			"sample_games/tags_0000.pgn",
+			"sample_games/tags_0001.pgn",
+			"sample_games/comment_0001.pgn",
+			"sample_games/fen_0000.pgn",
+			"sample_games/full_game_ii.pgn"
+		] {
+			let formatter = pgn_formatter::PgnFormatter::new();
+			for g in make_games(file.to_string()).iter() {
+				let s = formatter.format_game(g);
+				let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s.clone());
+				let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+				builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+				let h = builder.build_game().unwrap().unwrap();
+
+				assert_eq!(g.get_tree(), h.get_tree());
+				for (t, value) in g.get_tags().iter() {
+					assert_eq!(h.get_tag(t), Some(value));
+				}
+				assert_eq!(formatter.format_game(&h), s);
+			}
+		}
+	}
+
+	#[test]
+	fn export_format_nag_0000() {
+		let mut builder = make_builder("sample_games/nag_0000.pgn".to_string());
+		let g = builder.build_game_tree(0).unwrap().unwrap();
+
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_export_format(true)
+				.set_print_result(false)
+				.set_line_width(30)
+				.to_string(&g),
+			"1. e4 $1 e5 $6 2. Nf3 $14 Nc6\n\
+			$1 $18 3. Bb5 $18 (3. Bc4 $5\n\
+			Bc5 $200) 3... a6 $4 { Is this\n\
+			good? 1-0 } 1-0".to_string()
+		);
+	}
+
+	#[test]
+	fn export_format_full_game() {
+		let games = make_games("sample_games/full_game_ii.pgn".to_string());
+		let mut formatter = pgn_formatter::PgnFormatter::new();
+		formatter.set_export_format(true);
+
+		for width in [79, 40] {
+			formatter.set_line_width(width);
+			let s = formatter.format_game(&games[0]);
+
+			let (tags, movetext) = s.split_once("\n\n").unwrap();
+			assert!(tags.lines().all(|l| l.starts_with('[') && l.ends_with(']')));
+			assert!(movetext.ends_with(" 1/2-1/2\n"));
+			for line in movetext.lines() {
+				assert!(!line.is_empty());
+				assert!(line.chars().count() <= width);
+				assert!(!line.starts_with(' ') && !line.ends_with(' '));
+			}
+
+			// no token is split
+			let unwrapped = pgn_formatter::PgnFormatter::new()
+				.set_print_nags_as_symbols(false)
+				.to_string(games[0].get_tree());
+			assert_eq!(
+				movetext.split_whitespace().collect::<Vec<_>>(),
+				unwrapped.split_whitespace().collect::<Vec<_>>()
+			);
+
+			let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s);
+			let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+			builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+			let h = builder.build_game().unwrap().unwrap();
+			assert_eq!(games[0].get_tree(), h.get_tree());
+		}
+	}
+
+	#[test]
+	fn long_main_line() {
+		// 10,000 plies in which the knights go back and forth
+		let mut s = String::new();
+		for i in 0..2500 {
+			let n = 2*i + 1;
+			s.push_str(&format!("{n}. Nf3 Nf6 {}. Ng1 Ng8 ", n + 1));
+		}
+		s.push('*');
+
+		let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s.clone());
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let g = builder.build_game_tree(0).unwrap().unwrap();
+
+		assert_eq!(pgn_formatter::PgnFormatter::new().to_string(&g), s);
+	}
+
+	#[test]
+	fn deep_variations() {
+		let depth = 5000;
+		let mut s = String::new();
+		for _ in 0..depth {
+			s.push_str("1. e4 (");
+		}
+		s.push_str("1. d4");
+		for _ in 0..depth {
+			s.push(')');
+		}
+		s.push_str(" *");
+
+		let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s.clone());
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let g = builder.build_game_tree(0).unwrap().unwrap();
+
+		assert_eq!(pgn_formatter::PgnFormatter::new().to_string(&g), s);
+	}
+
+	#[test]
+	fn comment_0000() {
+		let g = make_game("sample_games/comment_0000.pgn".to_string());
+
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(&g),
+			"1. e4 { First line. Second line. New paragraph. } 1... e5 { [%clk 0:01:00] A } *"
+		);
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_print_comments_verbatim(true)
+				.to_string(&g),
+			"1. e4 {First line.\nSecond  line.\n\nNew paragraph.} 1... e5 { [%clk 0:01:00]  A } *"
+		);
+		// in export format, comments printed verbatim are not wrapped
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_print_comments_verbatim(true)
+				.set_export_format(true)
+				.set_line_width(12)
+				.to_string(&g),
+			"1. e4\n{First line.\nSecond  line.\n\nNew paragraph.}\n1... e5\n{ [%clk 0:01:00]  A }\n*"
+		);
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_export_format(true)
+				.set_line_width(12)
+				.to_string(&g),
+			"1. e4 {\nFirst line.\nSecond line.\nNew\nparagraph. }\n1... e5 {\n[%clk\n0:01:00] A }\n*"
+		);
+	}
+
+	#[test]
+	fn line_comments() {
+		let s = "1. e4 ; [%clk 0:01:00]  A\n1... e5 { B } *".to_string();
+		let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s);
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let g = builder.build_game_tree(0).unwrap().unwrap();
+
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(&g),
+			"1. e4 { [%clk 0:01:00] A } 1... e5 { B } *"
+		);
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_print_comments_verbatim(true)
+				.to_string(&g),
+			"1. e4 ; [%clk 0:01:00]  A\n1... e5 { B } *"
+		);
+	}
+
+	#[test]
+	fn line_comments_with_brace() {
+		let s = "1. e4 ; a } b\n1... e5 *".to_string();
+		let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s);
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let g = builder.build_game().unwrap().unwrap();
+
+		// the comment cannot be written in braces
+		let mut formatter = pgn_formatter::PgnFormatter::new();
+		assert_eq!(formatter.to_string(g.get_tree()), "1. e4 ; a } b\n1... e5 *");
+		for export in [false, true] {
+			for verbatim in [false, true] {
+				formatter.set_export_format(export).set_print_comments_verbatim(verbatim);
+				let s = formatter.format_game(&g);
+				let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s.clone());
+				let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+				builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+				let h = builder.build_game().unwrap().unwrap();
+
+				assert_eq!(g.get_tree(), h.get_tree());
+				assert_eq!(formatter.format_game(&h), s);
+			}
+		}
+
+		// the line break after the comment is kept in export format
+		formatter.set_export_format(true).set_print_comments_verbatim(false);
+		assert!(formatter.format_game(&g).ends_with("\n1. e4 ; a } b\n1... e5 *\n"));
+	}
+
+	#[test]
+	fn format_game_escapes() {
+		let games = make_games("sample_games/tags_0001.pgn".to_string());
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().format_game(&games[0]),
+			"[Event \"The \\\"Immortal\\\" Game\"]\n\
+			[Site \"London \\\\ England\"]\n\
+			[Date \"????.??.??\"]\n\
+			[Round \"?\"]\n\
+			[White \"Anderssen, Adolf\"]\n\
+			[Black \"Kieseritzky, Lionel\"]\n\
+			[Result \"1-0\"]\n\
+			[Annotator \"A \\\"quoted\\\" { bracket\"]\n\
+			\n\
+			1. e4 e5 2. f4 exf4 1-0\n".to_string()
+		);
+	}
+
+	#[test]
+	fn null_moves() {
+		let games = make_games("sample_games/nullmove_0000.pgn".to_string());
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(games[0].get_tree()),
+			"1. e4 e5 2. Nf3 Nc6 3. Bc4 (3. -- { What does Black threaten? } 3... d5) \
+			3... Nf6 4. Ng5 (4. Z0 Bc5) 4... d5 5. exd5 Na5 *"
+		);
+	}
+
+	#[test]
+	fn pre_move_comments() {
+		let games = make_games("sample_games/comment_0001.pgn".to_string());
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new().to_string(games[0].get_tree()),
+			"{ Annotated by Alice } 1. e4 e5 2. Nf3 ({ Better was } 2. f4 exf4) \
+			({ the quiet move } 2. Nc3) 2... Nc6 *"
+		);
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_print_comments_verbatim(true)
+				.to_string(games[0].get_tree()),
+			"{ Annotated by Alice } 1. e4 e5 2. Nf3 ({ Better was } 2. f4 exf4) \
+			(; the quiet move\n2. Nc3) 2... Nc6 *"
+		);
+		assert_eq!(
+			pgn_formatter::PgnFormatter::new()
+				.set_print_comments(false)
+				.to_string(games[0].get_tree()),
+			"1. e4 e5 2. Nf3 (2. f4 exf4) (2. Nc3) 2... Nc6 *"
+		);
+	}
 }
@@ -0,0 +1,196 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use std::io::BufReader;
+	use std::io::Cursor;
+
+	use pgn_parser::game;
+	use pgn_parser::pgn_error;
+	use pgn_parser::pgn_error::PgnErrorKind;
+	use pgn_parser::pgn_reader::PgnReader;
+	use pgn_parser::pgn_tree_builder;
+	use pgn_parser::pgn_tokenizer;
+	use pgn_parser::pgn_tokenizer::Span;
+
+	fn read_file(file: &str) -> Vec<Result<game::Game, pgn_error::PgnError>> {
+		let f = std::fs::File::open(file).expect("Failed to open file");
+		PgnReader::new(BufReader::new(f)).collect()
+	}
+
+	fn read_string(s: &str) -> Vec<Result<game::Game, pgn_error::PgnError>> {
+		PgnReader::new(Cursor::new(s.to_string())).collect()
+	}
+
+	fn build_file(file: &str) -> Vec<Result<game::Game, pgn_error::PgnError>> {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file.to_string()).expect("Failed to open file");
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.build_games()
+	}
+
+	fn same_as_builder(file: &str) {
+		let read = read_file(file);
+		let built = build_file(file);
+		assert_eq!(read.len(), built.len());
+		for (r, b) in read.iter().zip(built.iter()) {
+			match (r, b) {
+				(Ok(r), Ok(b)) => assert_eq!(r.get_tree(), b.get_tree()),
+				(Err(r), Err(b)) => assert_eq!(r.get_kind(), b.get_kind()),
+				_ => panic!("The reader and the builder disagree on {file}")
+			}
+		}
+	}
+
+	#[test]
+	fn same_games() {
+		same_as_builder("sample_games/multi_0000.pgn");
+		same_as_builder("sample_games/multi_0001.pgn");
+		same_as_builder("sample_games/multi_0002.pgn");
+		same_as_builder("sample_games/multi_0003.pgn");
+		same_as_builder("sample_games/fen_0000.pgn");
+		same_as_builder("sample_games/tags_0001.pgn");
+		same_as_builder("sample_games/full_game_ii.pgn");
+		same_as_builder("sample_games/0003-r.pgn");
+	}
+
+	#[test]
+	fn spans() {
+		let games = read_file("sample_games/multi_0001.pgn");
+		assert_eq!(games.len(), 2);
+
+		let tree = games[1].as_ref().unwrap().get_tree();
+		assert_eq!(tree.get_move_text(), "e4");
+		assert_eq!(tree.get_span(), &Some(Span::new(48, 2, 7, 4)));
+	}
+
+	#[test]
+	fn tag_inside_comment() {
+		let games = read_string(
+			"[Event \"A\"]\n\n1. e4 {\n[not a tag]\n} 1... e5 *\n\n[Event \"B\"]\n\n1. d4 *\n"
+		);
+		assert_eq!(games.len(), 2);
+
+		let tree = games[0].as_ref().unwrap().get_tree();
+		assert_eq!(tree.get_move_text(), "e4");
+		assert_eq!(tree.get_comments().len(), 1);
+		assert_eq!(tree.get_next_move().as_ref().unwrap().get_move_text(), "e5");
+
+		let tree = games[1].as_ref().unwrap().get_tree();
+		assert_eq!(tree.get_move_text(), "d4");
+	}
+
+	#[test]
+	fn errors() {
+		let games = read_file("sample_games/multi_0002.pgn");
+		assert_eq!(games.len(), 4);
+
+		let err = games[0].as_ref().err().unwrap();
+		assert!(matches!(err.get_kind(), PgnErrorKind::MoveNumberMismatch { .. }));
+		assert_eq!(err.get_span().unwrap().get_line(), 3);
+
+		let err = games[2].as_ref().err().unwrap();
+		assert!(matches!(err.get_kind(), PgnErrorKind::MoveNumberMismatch { .. }));
+		assert_eq!(err.get_span().unwrap().get_line(), 11);
+
+		assert!(games[3].is_ok());
+	}
+
+	#[test]
+	fn invalid_input() {
+		let mut reader = PgnReader::new(Cursor::new(vec![b'1', b'.', 0xff, b'\n']));
+		let err = reader.next().unwrap().err().unwrap();
+		assert!(matches!(err.get_kind(), PgnErrorKind::Io { .. }));
+		assert!(reader.next().is_none());
+	}
+
+	#[test]
+	fn empty_input() {
+		assert_eq!(read_string("").len(), 0);
+		assert_eq!(read_string("\n\n  \n").len(), 0);
+	}
+
+	#[test]
+	fn many_games() {
+		let mut s = String::new();
+		for i in 0..100 {
+			s.push_str(&format!("[Round \"{i}\"]\n\n1. e4 e5 2. Nf3 *\n\n"));
+		}
+
+		let mut num_games = 0;
+		for g in PgnReader::new(Cursor::new(s)) {
+			assert_eq!(g.unwrap().get_tree().get_move_text(), "e4");
+			num_games += 1;
+		}
+		assert_eq!(num_games, 100);
+	}
+
+	#[test]
+	fn line_comments() {
+		same_as_builder("sample_games/linecomment_0000.pgn");
+
+		let games = read_string("1. e4 ; {\n1... e5 *\n% [Event \"A\"]\n[Event \"B\"]\n\n1. d4 *\n");
+		assert_eq!(games.len(), 2);
+		assert_eq!(games[1].as_ref().unwrap().get_tree().get_move_text(), "d4");
+	}
+
+	#[test]
+	fn escaped_quotes() {
+		let games = read_string(
+			"[Event \"A \\\" {\"]\n\n1. e4 *\n\n[Event \"B\"]\n\n1. d4 *\n"
+		);
+		assert_eq!(games.len(), 2);
+		assert_eq!(games[0].as_ref().unwrap().get_event(), Some(&"A \" {".to_string()));
+		assert_eq!(games[1].as_ref().unwrap().get_tree().get_move_text(), "d4");
+	}
+
+	#[test]
+	fn lenient() {
+		let s = "[Event \"A\"]\n\n1. e4 3. e5 *\n\n[Event \"B\"]\n\n1. d4 ) d5 *\n";
+		assert!(read_string(s).iter().all(|g| g.is_err()));
+
+		let mut reader = PgnReader::new(Cursor::new(s.to_string()));
+		reader.set_options(*pgn_tree_builder::ParseOptions::new().set_strict(false));
+		let games: Vec<game::Game> = reader.map(|g| g.unwrap()).collect();
+		assert_eq!(games.len(), 2);
+		assert_eq!(games[0].get_warnings().len(), 1);
+		assert_eq!(games[1].get_warnings().len(), 1);
+		assert_eq!(
+			games[1].get_warnings()[0].get_span(),
+			&Some(Span::new(47, 1, 7, 7))
+		);
+	}
+}
@@ -0,0 +1,185 @@
+/*********************************************************************
+ *
+ * PGN parser -- A command line utility to process pgn-formatted files.
+ *
+ * Copyright (C) 2024
+ *
+ * This file is part of PGN Parser. The full code is available
+ * at:
+ *      https://github.com/lluisalemanypuig/pgn_parser.git
+ *
+ * PGN Parser is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * PGN Parser is distributed in the hope that it will be useful, but WITHOUT
+ * ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or
+ * FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero General Public
+ * License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with PGN Parser.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * Contact:
+ *
+ *     Lluís Alemany Puig
+ *         email: lluis.alemany.puig@gmail.com
+ *         https://github.com/lluisalemanypuig
+ *         lluisalemanypuig.github.io
+ *
+ ********************************************************************/
+
+#[cfg(test)]
+mod tests {
+
+	use pgn_parser::nag;
+	use pgn_parser::pgn_tokenizer;
+	use pgn_parser::pgn_tokenizer::Span;
+	use pgn_parser::pgn_tokenizer::TokenType;
+
+	#[test]
+	fn spans_single_line() {
+		let (tokens, types, spans) = pgn_tokenizer::tokenize("1. d4 (1. e4) 1-0".to_string());
+		assert_eq!(tokens, vec!["1.", "d4", "(", "1.", "e4", ")", "1-0"]);
+		assert_eq!(types[2], TokenType::VariantDelim { open: true });
+		assert_eq!(
+			spans,
+			vec![
+				Span::new(0, 2, 1, 1),
+				Span::new(3, 2, 1, 4),
+				Span::new(6, 1, 1, 7),
+				Span::new(7, 2, 1, 8),
+				Span::new(10, 2, 1, 11),
+				Span::new(12, 1, 1, 13),
+				Span::new(14, 3, 1, 15),
+			]
+		);
+	}
+
+	#[test]
+	fn spans_tags() {
+		let (tokens, _, spans) =
+			pgn_tokenizer::tokenize("[Event \"A\"]\n[Site \"B\"]\n".to_string());
+		assert_eq!(tokens, vec!["[", "Event", "\"A\"", "]", "[", "Site", "\"B\"", "]"]);
+		assert_eq!(spans[2], Span::new(7, 3, 1, 8));
+		assert_eq!(spans[4], Span::new(12, 1, 2, 1));
+		assert_eq!(spans[6], Span::new(18, 3, 2, 7));
+		assert_eq!(spans[7].get_end(), 22);
+	}
+
+	#[test]
+	fn quoted_strings() {
+		let (tokens, types, spans) =
+			pgn_tokenizer::tokenize("[Event \"A (b) {c} [d]\"]".to_string());
+		assert_eq!(tokens, vec!["[", "Event", "\"A (b) {c} [d]\"", "]"]);
+		assert_eq!(types[2], TokenType::Text);
+		assert_eq!(spans[2], Span::new(7, 15, 1, 8));
+	}
+
+	#[test]
+	fn span_0000() {
+		let (tokens, _, spans) =
+			pgn_tokenizer::tokenize_file("sample_games/span_0000.pgn".to_string())
+			.expect("Failed to open file");
+
+		// the body of a comment is kept as it is
+		assert_eq!(tokens, vec!["1.", "d4", "{", " 新しい\nゲーム ", "}", "1...", "d5"]);
+		// offsets are in bytes, columns are in characters
+		assert_eq!(spans[3], Span::new(7, 21, 1, 8));
+		assert_eq!(spans[4], Span::new(28, 1, 2, 5));
+		assert_eq!(spans[5], Span::new(30, 4, 3, 1));
+		assert_eq!(spans[6], Span::new(35, 2, 3, 6));
+	}
+
+	#[test]
+	fn nags() {
+		let (tokens, types, spans) =
+			pgn_tokenizer::tokenize("1. e4!? $14 e5 +- {Good?}".to_string());
+		assert_eq!(tokens, vec!["1.", "e4", "!?", "$14", "e5", "+-", "{", "Good?", "}"]);
+		assert_eq!(types[1], TokenType::Text);
+		assert_eq!(types[2], TokenType::Nag { value: 5 });
+		assert_eq!(types[3], TokenType::Nag { value: 14 });
+		assert_eq!(types[5], TokenType::Nag { value: 18 });
+		// glyphs are never recognised inside comments
+		assert_eq!(types[7], TokenType::Text);
+
+		assert_eq!(spans[1], Span::new(3, 2, 1, 4));
+		assert_eq!(spans[2], Span::new(5, 2, 1, 6));
+	}
+
+	#[test]
+	fn nag_meanings() {
+		assert_eq!(nag::parse_nag("$4"), Some(nag::Nag::new(4)));
+		assert_eq!(nag::parse_nag("??"), Some(nag::Nag::new(4)));
+		assert_eq!(nag::parse_nag("±"), Some(nag::Nag::new(16)));
+		assert_eq!(nag::parse_nag("$256"), None);
+		assert_eq!(nag::parse_nag("$"), None);
+		assert_eq!(nag::parse_nag("Nf3"), None);
+
+		assert_eq!(nag::Nag::new(4).get_description(), "very poor move");
+		assert_eq!(nag::Nag::new(19).get_description(), "Black has a decisive advantage");
+		assert_eq!(nag::Nag::new(22).get_description(), "White is in zugzwang");
+		assert_eq!(nag::Nag::new(139).get_description(), "Black has severe time control pressure");
+		assert_eq!(nag::Nag::new(200).get_description(), "unassigned annotation");
+
+		assert_eq!(nag::Nag::new(16).get_symbol(), Some("+/-"));
+		assert_eq!(nag::Nag::new(36).get_symbol(), None);
+
+		assert_eq!(
+			nag::split_suffix_annotation("Rg8??"),
+			Some(("Rg8", nag::Nag::new(4)))
+		);
+		assert_eq!(nag::split_suffix_annotation("Rg8"), None);
+		assert_eq!(nag::split_suffix_annotation("!!"), None);
+	}
+
+	#[test]
+	fn line_comments() {
+		let (tokens, types, spans) =
+			pgn_tokenizer::tokenize("1. e4; a {b\r\n% x y\ne5 ;".to_string());
+		assert_eq!(tokens, vec!["1.", "e4", " a {b", "e5", ""]);
+		assert_eq!(types[2], TokenType::LineComment);
+		assert_eq!(types[4], TokenType::LineComment);
+		assert_eq!(spans[2], Span::new(5, 6, 1, 6));
+		assert_eq!(spans[3], Span::new(19, 2, 3, 1));
+		assert_eq!(spans[4], Span::new(22, 1, 3, 4));
+	}
+
+	#[test]
+	fn escape_lines() {
+		let (tokens, _, _) =
+			pgn_tokenizer::tokenize("%[Event \"A\"]\n1. e4 %e5\n{\n% c}".to_string());
+		assert_eq!(tokens, vec!["1.", "e4", "%e5", "{", "\n% c", "}"]);
+	}
+
+	#[test]
+	fn escaped_quotes() {
+		let (tokens, types, spans) =
+			pgn_tokenizer::tokenize("[Event \"A \\\"b\\\" \\\\\"]".to_string());
+		assert_eq!(tokens, vec!["[", "Event", "\"A \\\"b\\\" \\\\\"", "]"]);
+		assert_eq!(types[2], TokenType::Text);
+		assert_eq!(spans[2], Span::new(7, 12, 1, 8));
+
+		assert_eq!(pgn_tokenizer::unquote_string(&tokens[2]), "A \"b\" \\");
+		assert_eq!(pgn_tokenizer::quote_string("A \"b\" \\"), tokens[2]);
+	}
+
+	#[test]
+	fn null_moves() {
+		let (tokens, types, _) = pgn_tokenizer::tokenize("1. -- Z0 2. e4 { -- }".to_string());
+		assert_eq!(tokens, vec!["1.", "--", "Z0", "2.", "e4", "{", " -- ", "}"]);
+		assert_eq!(types[1], TokenType::NullMove);
+		assert_eq!(types[2], TokenType::NullMove);
+		assert_eq!(types[4], TokenType::Text);
+		assert_eq!(types[6], TokenType::Text);
+	}
+
+	#[test]
+	fn large_move_numbers() {
+		let (tokens, types, _) = pgn_tokenizer::tokenize("65535. e4 100000. e5 *".to_string());
+		assert_eq!(tokens, vec!["65535.", "e4", "100000.", "e5", "*"]);
+		assert_eq!(types[0], TokenType::MoveNumber { id: 65535, side: pgn_tokenizer::Side::White });
+		assert_eq!(types[2], TokenType::Text);
+	}
+}
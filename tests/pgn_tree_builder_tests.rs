@@ -38,28 +38,29 @@
 	use pgn_parser::pgn_tree_builder;
 	use pgn_parser::pgn_tokenizer;
 
-	use std::io::BufRead;
-
 	fn make_builder(file: String) -> pgn_tree_builder::PGNTreeBuilder {
-		let mut entire_file_str = String::new();
-
-		let file = std::fs::File::open(file).expect("Failed to open file");
-		let reader = std::io::BufReader::new(file);
-		for line in reader.lines() {
-			entire_file_str.push_str( line.unwrap().trim() );
-		}
-		
-		let (all_tokens, all_token_types) = pgn_tokenizer::tokenize(entire_file_str);
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
 		
 		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
-		builder.set_token_list(all_tokens, all_token_types);
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+
+		builder
+	}
+
+	fn make_builder_from(text: &str) -> pgn_tree_builder::PGNTreeBuilder {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize(text.to_string());
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
 
 		builder
 	}
 
 	fn make_game(file: String) -> game::GameTree {
 		let mut builder = make_builder(file);
-		if let Some(g) = builder.build_game_tree(0) {
+		if let Ok(Some(g)) = builder.build_game_tree(0) {
 			return g;
 		}
 
@@ -1189,5 +1190,446 @@
 		assert_eq!(make_game("sample_games/0017-r.pgn".to_string()), g);
 	}
 
+	// -------------------------------------------------
+
+	fn make_games(file: String) -> Vec<game::Game> {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize_file(file).expect("Failed to open file");
+
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.build_games()
+			.into_iter()
+			.map(|g| g.unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn multi_0000() {
+		let games = make_games("sample_games/multi_0000.pgn".to_string());
+		assert_eq!(games.len(), 3);
+
+		let mut g = make_game_0001();
+		g
+			.get_next_move_mut().as_mut().unwrap()
+			.set_next_move(
+				game::GameTree::new_data(
+					String::from("c4"),
+					false,
+					2,
+					Some(pgn_tokenizer::Side::White),
+					vec![],
+					Some(Box::new(result_move("1-0"))),
+					vec![],
+				)
+			);
+		assert_eq!(games[0].get_tree(), &g);
+
+		let g = game::GameTree::new_data(
+			String::from("e4"),
+			false,
+			1,
+			Some(pgn_tokenizer::Side::White),
+			vec![],
+			Some(Box::new(
+				game::GameTree::new_data(
+					String::from("e5"),
+					false,
+					1,
+					Some(pgn_tokenizer::Side::Black),
+					vec![],
+					Some(Box::new(result_move("0-1"))),
+					vec![],
+				)
+			)),
+			vec![make_game_0001()]
+		);
+		assert_eq!(games[1].get_tree(), &g);
+
+		let g = game::GameTree::new_data(
+			String::from("c4"),
+			false,
+			1,
+			Some(pgn_tokenizer::Side::White),
+			vec![make_comment("A A", vec![])],
+			Some(Box::new(result_move("*"))),
+			vec![],
+		);
+		assert_eq!(games[2].get_tree(), &g);
+	}
+
+	#[test]
+	fn multi_0001() {
+		let games = make_games("sample_games/multi_0001.pgn".to_string());
+		assert_eq!(games.len(), 2);
+
+		assert_eq!(games[0].get_tree(), &make_game_0001());
+
+		let mut g = make_game_0001();
+		g.set_move_text("e4".to_string(), &pgn_tokenizer::Side::White, 1);
+		g
+			.get_next_move_mut().as_mut().unwrap()
+			.set_move_text("e5".to_string(), &pgn_tokenizer::Side::Black, 1);
+		g
+			.get_next_move_mut().as_mut().unwrap()
+			.set_next_move(result_move("1/2-1/2"));
+		assert_eq!(games[1].get_tree(), &g);
+	}
+
+	#[test]
+	fn multi_0003() {
+		let games = make_games("sample_games/multi_0003.pgn".to_string());
+		assert_eq!(games.len(), 3);
+
+		// the comments after a termination marker are kept with it
+		for (g, text) in games.iter().zip(["trailing", "note", "end"]) {
+			let result = g.get_tree().get_next_move().as_ref().unwrap();
+			assert!(result.is_result());
+			assert_eq!(result.get_comments().len(), 1);
+			assert_eq!(result.get_comments()[0].get_text(), text);
+		}
+		assert_eq!(games[1].get_tag(&game::TagType::Event), Some(&"Second".to_string()));
+		assert_eq!(games[2].get_tag(&game::TagType::Event), Some(&"Third".to_string()));
+	}
+
+	#[test]
+	fn comments_between_games() {
+		let mut builder = make_builder_from("{first} [Event \"a\"] 1. e4 * {second} {third}");
+		let g = builder.build_game().unwrap().unwrap();
+		assert_eq!(g.get_tag(&game::TagType::Event), Some(&"a".to_string()));
+		assert_eq!(g.get_tree().get_next_move().as_ref().unwrap().get_comments().len(), 2);
+		assert!(builder.build_game().unwrap().is_none());
+
+		// a file with nothing but comments has no games
+		let mut builder = make_builder_from("{only} ; a comment");
+		assert!(builder.build_game().unwrap().is_none());
+	}
+
+	#[test]
+	fn multi_single_game() {
+		let games = make_games("sample_games/0017-r.pgn".to_string());
+		assert_eq!(games.len(), 1);
+
+		let mut builder = make_builder("sample_games/0017-r.pgn".to_string());
+		assert!(builder.build_game().unwrap().is_some());
+		assert!(builder.build_game().unwrap().is_none());
+	}
+
+	// -------------------------------------------------
+
+	#[test]
+	fn span_0000() {
+		let g = make_game("sample_games/span_0000.pgn".to_string());
+		assert_eq!(g.get_span(), &Some(pgn_tokenizer::Span::new(3, 2, 1, 4)));
+
+		let next = g.get_next_move().as_ref().unwrap();
+		assert_eq!(next.get_move_text(), "d5");
+		assert_eq!(next.get_span(), &Some(pgn_tokenizer::Span::new(35, 2, 3, 6)));
+	}
+
+	// -------------------------------------------------
+
+	#[test]
+	fn nag_0000() {
+		let g = make_game("sample_games/nag_0000.pgn".to_string());
+
+		let values = |g: &game::GameTree| -> Vec<u8> {
+			g.get_nags().iter().map(|n| n.get_value()).collect()
+		};
+
+		assert_eq!(g.get_move_text(), "e4");
+		assert_eq!(values(&g), vec![1]);
+
+		let g = g.get_next_move().as_ref().unwrap();
+		assert_eq!(g.get_move_text(), "e5");
+		assert_eq!(values(g), vec![6]);
+
+		let g = g.get_next_move().as_ref().unwrap();
+		assert_eq!(g.get_move_text(), "Nf3");
+		assert_eq!(values(g), vec![14]);
+
+		let g = g.get_next_move().as_ref().unwrap();
+		assert_eq!(g.get_move_text(), "Nc6");
+		assert_eq!(values(g), vec![1, 18]);
+
+		let g = g.get_next_move().as_ref().unwrap();
+		assert_eq!(g.get_move_text(), "Bb5");
+		assert_eq!(values(g), vec![18]);
+
+		let var = &g.get_variations()[0];
+		assert_eq!(var.get_move_text(), "Bc4");
+		assert_eq!(values(var), vec![5]);
+		let var = var.get_next_move().as_ref().unwrap();
+		assert_eq!(var.get_move_text(), "Bc5");
+		assert_eq!(values(var), vec![200]);
+
+		let g = g.get_next_move().as_ref().unwrap();
+		assert_eq!(g.get_move_text(), "a6");
+		assert_eq!(values(g), vec![4]);
+		assert_eq!(g.get_comments()[0].get_text(), "Is this good? 1-0");
+	}
+
+	#[test]
+	fn tags_0000() {
+		let games = make_games("sample_games/tags_0000.pgn".to_string());
+		assert_eq!(games.len(), 1);
+		let g = &games[0];
+
+		assert_eq!(g.get_event(), Some(&"Casual game".to_string()));
+		assert_eq!(g.get_site(), Some(&"Barcelona".to_string()));
+		assert_eq!(g.get_date(), Some(&"2024.05.01".to_string()));
+		assert_eq!(g.get_round(), Some(&"1".to_string()));
+		assert_eq!(g.get_white(), Some(&"Alice".to_string()));
+		assert_eq!(g.get_black(), Some(&"Bob".to_string()));
+		assert_eq!(g.get_result(), Some(&"1-0".to_string()));
+		assert_eq!(g.get_tag(&game::TagType::Opening), Some(&"Queen's Gambit".to_string()));
+		assert_eq!(
+			g.get_tag(&game::TagType::Other("Mood".to_string())),
+			Some(&"Good".to_string())
+		);
+		assert_eq!(g.get_tag(&game::TagType::ECO), None);
+
+		let names: Vec<String> = g.get_tags().iter()
+			.map(|(t, _)| game::tag_to_string(t))
+			.collect();
+		assert_eq!(
+			names,
+			vec![
+				"Event", "Site", "Date", "Round", "White", "Black", "Result",
+				"WhiteElo", "Opening", "Mood"
+			]
+		);
+	}
+
+	#[test]
+	fn tags_modify() {
+		let mut g = game::Game::new();
+		assert_eq!(g.get_event(), None);
+
+		g.set_tag(game::TagType::Event, "A".to_string());
+		g.add_game_tag((game::TagType::Site, "B".to_string()));
+		g.add_game_tag((game::TagType::Event, "C".to_string()));
+		assert_eq!(g.get_event(), Some(&"A".to_string()));
+
+		g.set_tag(game::TagType::Event, "D".to_string());
+		assert_eq!(g.get_event(), Some(&"D".to_string()));
+		assert_eq!(g.get_tags().len(), 3);
+
+		assert_eq!(g.remove_tag(&game::TagType::Event), Some("D".to_string()));
+		assert_eq!(g.get_event(), None);
+		assert_eq!(g.get_tags(), &vec![(game::TagType::Site, "B".to_string())]);
+		assert_eq!(g.remove_tag(&game::TagType::Event), None);
+
+		for t in game::SEVEN_TAG_ROSTER.iter() {
+			assert_eq!(&game::classify(game::tag_to_string(t)), t);
+		}
+	}
+
+	// A game of 10,000 plies in which the knights go back and forth.
+	fn long_game() -> String {
+		let mut s = String::new();
+		for i in 0..2500 {
+			let n = 2*i + 1;
+			s.push_str(&format!("{n}. Nf3 Nf6 {}. Ng1 Ng8 ", n + 1));
+		}
+		s.push('*');
+		s
+	}
+
+	fn build_from_string(s: String) -> game::Game {
+		let (all_tokens, all_token_types, all_token_spans) = pgn_tokenizer::tokenize(s);
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		builder.build_game().unwrap().unwrap()
+	}
+
+	#[test]
+	fn long_main_line() {
+		let g = build_from_string(long_game());
+
+		let mut num_plies = 0;
+		let mut node = g.get_tree();
+		while !node.is_result() {
+			num_plies += 1;
+			node = node.get_next_move().as_ref().unwrap();
+		}
+		assert_eq!(num_plies, 10000);
+
+		let h = build_from_string(long_game());
+		assert_eq!(g.get_tree(), h.get_tree());
+	}
+
+	#[test]
+	fn deep_variations() {
+		// every variation contains the next one
+		let depth = 5000;
+		let mut s = String::new();
+		for _ in 0..depth {
+			s.push_str("1. e4 (");
+		}
+		s.push_str("1. d4");
+		for _ in 0..depth {
+			s.push(')');
+		}
+		s.push_str(" *");
+
+		let mut g = build_from_string(s);
+		assert_eq!(g.replay_moves(), vec![]);
+		let mut num_variations = 0;
+		let mut node = g.get_tree();
+		while let Some(var) = node.get_variations().first() {
+			num_variations += 1;
+			node = var;
+		}
+		assert_eq!(num_variations, depth);
+		assert_eq!(node.get_move_text(), "d4");
+		assert!(node.get_position().is_some());
+	}
+
+	#[test]
+	fn comment_0000() {
+		let g = make_game("sample_games/comment_0000.pgn".to_string());
+
+		let c = &g.get_comments()[0];
+		assert_eq!(c.get_raw_text(), "First line.\nSecond  line.\n\nNew paragraph.");
+		assert_eq!(c.get_text(), "First line. Second line. New paragraph.");
+
+		let c = &g.get_next_move().as_ref().unwrap().get_comments()[0];
+		assert_eq!(c.get_raw_text(), " [%clk 0:01:00]  A ");
+		assert_eq!(c.get_text(), "A");
+		assert_eq!(c.get_tags(), &vec![(comment::TagType::Clock, "0:01:00".to_string())]);
+	}
+
+	#[test]
+	fn result_0000() {
+		use pgn_tokenizer::ResultType;
+
+		let games = make_games("sample_games/result_0000.pgn".to_string());
+		assert_eq!(games.len(), 4);
+
+		assert_eq!(games[0].result(), ResultType::White);
+		assert_eq!(games[0].check_result(), Ok(()));
+
+		assert_eq!(games[1].result(), ResultType::Black);
+		let mismatch = games[1].check_result().unwrap_err();
+		assert_eq!(mismatch.get_tag_value(), "1/2-1/2");
+		assert_eq!(mismatch.get_terminator(), ResultType::Black);
+		assert_eq!(
+			mismatch.to_string(),
+			"The Result tag '1/2-1/2' disagrees with the termination marker '0-1'"
+		);
+
+		assert_eq!(games[2].result(), ResultType::Draw);
+		assert_eq!(games[2].check_result(), Ok(()));
+
+		assert_eq!(games[3].result(), ResultType::Unknown);
+		assert_eq!(games[3].get_tree().get_result(), Some(ResultType::Unknown));
+		assert_eq!(games[3].check_result(), Ok(()));
+
+		// the result is kept in the node of the termination marker
+		let mut last = games[0].get_tree();
+		while let Some(next) = last.get_next_move() {
+			assert_eq!(last.get_result(), None);
+			last = next;
+		}
+		assert_eq!(last.get_result(), Some(ResultType::White));
+	}
+
+	#[test]
+	fn result_without_terminator() {
+		use pgn_tokenizer::ResultType;
+
+		let games = make_games("sample_games/multi_0001.pgn".to_string());
+		assert_eq!(games[0].result(), ResultType::Unknown);
+		assert_eq!(games[1].result(), ResultType::Draw);
+
+		let mut g = game::Game::new();
+		assert_eq!(g.result(), ResultType::Unknown);
+		g.set_tag(game::TagType::Result, "1-0".to_string());
+		assert_eq!(
+			g.check_result(),
+			Err(game::ResultMismatch::new("1-0".to_string(), ResultType::Unknown))
+		);
+	}
+
+	#[test]
+	fn linecomment_0000() {
+		let games = make_games("sample_games/linecomment_0000.pgn".to_string());
+		assert_eq!(games.len(), 1);
+		let g = &games[0];
+
+		let names: Vec<String> = g.get_tags().iter()
+			.map(|(t, _)| game::tag_to_string(t))
+			.collect();
+		assert_eq!(names, vec!["Event", "Site"]);
+
+		let tree = g.get_tree();
+		let c = &tree.get_comments()[0];
+		assert_eq!(c.get_style(), comment::CommentStyle::RestOfLine);
+		assert_eq!(c.get_raw_text(), " the king's pawn {not a brace comment");
+		assert_eq!(c.get_text(), "the king's pawn {not a brace comment");
+
+		let tree = tree.get_next_move().as_ref().unwrap().get_next_move().as_ref().unwrap();
+		assert_eq!(tree.get_move_text(), "Nf3");
+		let c = &tree.get_comments()[0];
+		assert_eq!(c.get_style(), comment::CommentStyle::RestOfLine);
+		assert_eq!(c.get_text(), "developing");
+		assert_eq!(c.get_clock(), Some(std::time::Duration::from_secs(300)));
+
+		let tree = tree.get_next_move().as_ref().unwrap();
+		assert_eq!(tree.get_move_text(), "Nc6");
+		assert_eq!(tree.get_comments().len(), 0);
+	}
+
+	#[test]
+	fn tags_0001() {
+		let games = make_games("sample_games/tags_0001.pgn".to_string());
+		assert_eq!(games.len(), 1);
+		let g = &games[0];
+
+		assert_eq!(g.get_event(), Some(&"The \"Immortal\" Game".to_string()));
+		assert_eq!(g.get_site(), Some(&"London \\ England".to_string()));
+		assert_eq!(
+			g.get_tag(&game::TagType::Annotator),
+			Some(&"A \"quoted\" { bracket".to_string())
+		);
+		assert_eq!(g.get_tree().get_move_text(), "e4");
+		assert!(g.check_result().is_ok());
+	}
+
+	#[test]
+	fn comment_0001() {
+		let games = make_games("sample_games/comment_0001.pgn".to_string());
+		assert_eq!(games.len(), 1);
+
+		let tree = games[0].get_tree();
+		assert_eq!(tree.get_move_text(), "e4");
+		assert_eq!(tree.get_comments().len(), 0);
+		assert_eq!(tree.get_pre_comments().len(), 1);
+		assert_eq!(tree.get_pre_comments()[0].get_text(), "Annotated by Alice");
+
+		let tree = tree.get_next_move().as_ref().unwrap().get_next_move().as_ref().unwrap();
+		assert_eq!(tree.get_move_text(), "Nf3");
+		assert_eq!(tree.get_comments().len(), 0);
+		assert_eq!(tree.get_pre_comments().len(), 0);
+
+		let var = &tree.get_variations()[0];
+		assert_eq!(var.get_move_text(), "f4");
+		assert_eq!(var.get_pre_comments()[0].get_text(), "Better was");
+
+		let var = &tree.get_variations()[1];
+		assert_eq!(var.get_move_text(), "Nc3");
+		assert_eq!(var.get_pre_comments()[0].get_text(), "the quiet move");
+		assert_eq!(var.get_pre_comments()[0].get_style(), comment::CommentStyle::RestOfLine);
+	}
+
+	#[test]
+	fn comment_before_missing_move_number() {
+		let (all_tokens, all_token_types, all_token_spans) =
+			pgn_tokenizer::tokenize("1. e4 ( { A } e3 ) e5 *".to_string());
+		let mut builder = pgn_tree_builder::PGNTreeBuilder::new();
+		builder.set_token_list(all_tokens, all_token_types, all_token_spans);
+		let err = builder.build_game_tree(0).err().unwrap();
+		assert_eq!(err.get_token_index(), 6);
+	}
 }
- 
\ No newline at end of file